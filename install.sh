@@ -52,18 +52,8 @@ fi
 # 3. Install Desktop Entry
 echo "Installing .desktop file to $DEST_DESKTOP..."
 
-sudo tee "$DEST_DESKTOP/$APP_NAME.desktop" > /dev/null << EOF
-[Desktop Entry]
-Name=Rusty Browser
-GenericName=Web Browser
-Comment=A privacy-focused, high-performance web browser built with Rust and WebKit.
-Exec=$DEST_BIN/$APP_NAME %U
-Icon=$ICON_NAME
-Type=Application
-Categories=Network;WebBrowser;
-StartupNotify=true
-MimeType=text/html;text/xml;application/xhtml+xml;application/vnd.mozilla.xul+xml;application/x-mimearchive;x-scheme-handler/http;x-scheme-handler/https;x-scheme-handler/ftp;
-EOF
+# The binary writes its own entry (MIME types plus New Window / New Private Window actions).
+"$DEST_BIN/$APP_NAME" --desktop-entry | sed "s/^Icon=.*/Icon=$ICON_NAME/" | sudo tee "$DEST_DESKTOP/$APP_NAME.desktop" > /dev/null
 
 # 4. Update Icon/MIME Cache
 echo "Updating icon and desktop database..."
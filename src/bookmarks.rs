@@ -0,0 +1,57 @@
+// --- BOOKMARKS ---
+use serde::{Serialize, Deserialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{load_json, save_json};
+
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Bookmark {
+    pub url: String,
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Bookmarks {
+    items: Vec<Bookmark>,
+}
+
+pub type SharedBookmarks = Rc<RefCell<Bookmarks>>;
+
+impl Bookmarks {
+    pub fn load() -> Self {
+        load_json(BOOKMARKS_FILE)
+    }
+
+    fn save(&self) {
+        save_json(BOOKMARKS_FILE, self);
+    }
+
+    pub fn items(&self) -> &[Bookmark] {
+        &self.items
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.items.iter().any(|b| b.url == url)
+    }
+
+    // Returns whether the page is bookmarked afterwards.
+    pub fn toggle(&mut self, url: &str, title: &str) -> bool {
+        let added = if self.contains(url) {
+            self.items.retain(|b| b.url != url);
+            false
+        } else {
+            self.items.push(Bookmark { url: url.to_string(), title: title.to_string() });
+            true
+        };
+        self.save();
+        added
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        self.items.retain(|b| b.url != url);
+        self.save();
+    }
+}
@@ -0,0 +1,611 @@
+// --- BROWSER ---
+use gtk::prelude::*;
+use gtk::{gdk, gio, glib, Application, Notebook, Widget, Window};
+use webkit6::prelude::*;
+use webkit6::{WebView, HardwareAccelerationPolicy, WebContext, NetworkSession, UserContentManager, UserStyleSheet, UserContentInjectedFrames, UserStyleLevel,
+    PolicyDecisionType, NavigationAction, NavigationPolicyDecision, NavigationType, LoadEvent};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::rc::{Rc, Weak};
+
+use crate::bookmarks::{Bookmarks, SharedBookmarks};
+use crate::crashes::{self, CrashLog, SharedCrashLog};
+use crate::downloads::Downloads;
+use crate::error_pages::ErrorPages;
+use crate::history::{self, History, SharedHistory};
+use crate::https_only::{self, SessionExceptions};
+use crate::internal::InternalPages;
+use crate::notifications::NotificationBridge;
+use crate::permissions::{self, PermissionKind, SharedPermissions, SitePermissions};
+use crate::session::Session;
+use crate::site_data::{self, FilterSlot};
+use crate::tab_groups::TabGroup;
+use crate::tab_label::{self, TabLabel};
+use crate::tab_menu::{self, TabMenu};
+use crate::tab_page;
+use crate::window::{self, BrowserWindow};
+use crate::{clear_data, favicons, hibernation, newtab, page_menu, tls};
+use crate::{is_amnesia_session, load_config, navigation_uri, profile_storage_dirs, AppConfig};
+
+// window.open() sizes that leave width or height out.
+const POPUP_SIZE: (i32, i32) = (640, 480);
+
+// The size a pop-up asked for; a side it left out gets the default.
+fn popup_size(requested: Option<(i32, i32)>) -> (i32, i32) {
+    let (width, height) = requested.unwrap_or(POPUP_SIZE);
+    (if width > 0 { width } else { POPUP_SIZE.0 }, if height > 0 { height } else { POPUP_SIZE.1 })
+}
+
+// Whether a link click opens a new tab, and if so whether it stays in the
+// background; Shift brings it to the front.
+fn new_tab_click(button: u32, modifiers: gdk::ModifierType) -> Option<bool> {
+    if button != gdk::BUTTON_MIDDLE && !modifiers.contains(gdk::ModifierType::CONTROL_MASK) { return None; }
+    Some(!modifiers.contains(gdk::ModifierType::SHIFT_MASK))
+}
+
+// Everything one profile shares across its windows. Created on the first
+// activate and kept for the life of the application.
+pub struct Browser {
+    app: glib::WeakRef<Application>,
+    pub state: Rc<RefCell<AppConfig>>,
+    pub context: WebContext,
+    pub session: Rc<NetworkSession>,
+    pub permissions: SharedPermissions,
+    pub notifications: Rc<NotificationBridge>,
+    pub history: SharedHistory,
+    pub bookmarks: SharedBookmarks,
+    pub crashes: SharedCrashLog,
+    pub downloads: Rc<Downloads>,
+    cookie_filter: FilterSlot,
+    internal: Rc<InternalPages>,
+    https_exceptions: SessionExceptions,
+    error_pages: Rc<ErrorPages>,
+    tab_menu: Rc<TabMenu>,
+    windows: RefCell<Vec<Rc<BrowserWindow>>>,
+    exit_cleared: Cell<bool>,
+}
+
+impl Browser {
+    pub fn new(app: &Application) -> Rc<Self> {
+        let state = Rc::new(RefCell::new(load_config()));
+
+        // --- PERSISTENCE ---
+        // In an amnesia session even "normal" tabs get an ephemeral network session:
+        // cookies, disk cache, local storage and IndexedDB all stay in memory.
+        let context = WebContext::default().unwrap();
+        let session = if is_amnesia_session() {
+            NetworkSession::new_ephemeral()
+        } else if let Some((data, cache)) = profile_storage_dirs() {
+            NetworkSession::new(Some(&data), Some(&cache))
+        } else {
+            NetworkSession::default().unwrap()
+        };
+        favicons::enable(&session);
+        let session = Rc::new(session);
+
+        let permissions: SharedPermissions = Rc::new(RefCell::new(SitePermissions::load()));
+        let perms_notify = permissions.clone();
+        context.connect_initialize_notification_permissions(move |ctx| {
+            let (allowed, denied) = permissions::notification_origins(&perms_notify.borrow());
+            ctx.initialize_notification_permissions(&allowed, &denied);
+        });
+        let notifications = NotificationBridge::new(app, state.clone(), permissions.clone());
+
+        let history: SharedHistory = History::load();
+        let bookmarks: SharedBookmarks = Rc::new(RefCell::new(Bookmarks::load()));
+        let crashes: SharedCrashLog = Rc::new(RefCell::new(CrashLog::load()));
+        let downloads = Downloads::new();
+        downloads.watch(&session);
+
+        let browser = Rc::new_cyclic(|weak: &Weak<Browser>| {
+            // --- INTERNAL PAGES ---
+            let weak_settings = weak.clone();
+            let on_settings_changed: Rc<dyn Fn()> = Rc::new(move || {
+                if let Some(browser) = weak_settings.upgrade() { browser.settings_changed(); }
+            });
+            let internal = InternalPages::new(state.clone(), history.clone(), bookmarks.clone(), crashes.clone(), downloads.clone(), session.clone(), on_settings_changed);
+            internal.register(&context);
+
+            Browser {
+                app: app.downgrade(),
+                state,
+                context,
+                session,
+                permissions,
+                notifications,
+                history,
+                bookmarks,
+                crashes,
+                downloads,
+                cookie_filter: Rc::new(RefCell::new(None)),
+                internal,
+                https_exceptions: Rc::new(RefCell::new(HashSet::new())),
+                error_pages: ErrorPages::new(),
+                tab_menu: TabMenu::new(app, weak.clone()),
+                windows: RefCell::new(Vec::new()),
+                exit_cleared: Cell::new(false),
+            }
+        });
+        browser.apply_cookies();
+        hibernation::watch(&browser);
+
+        let new_window = gio::SimpleAction::new("new-window", None);
+        let weak = Rc::downgrade(&browser);
+        new_window.connect_activate(move |_, _| {
+            if let Some(browser) = weak.upgrade() {
+                let url = newtab::new_tab_url(&browser.state.borrow());
+                browser.open_window(&url);
+            }
+        });
+        app.add_action(&new_window);
+        app.set_accels_for_action("app.new-window", &["<Primary>n"]);
+        browser
+    }
+
+    pub fn app(&self) -> Option<Application> {
+        self.app.upgrade()
+    }
+
+    // --- WINDOWS ---
+    // An empty window; callers add tabs and present it.
+    pub fn new_window(self: &Rc<Self>) -> Rc<BrowserWindow> {
+        let window = BrowserWindow::new(self);
+        self.windows.borrow_mut().push(window.clone());
+        window
+    }
+
+    pub fn open_window(self: &Rc<Self>, url: &str) -> Rc<BrowserWindow> {
+        let window = self.new_window();
+        window.create_tab(url, false);
+        window.present();
+        window
+    }
+
+    pub fn active_window(&self) -> Option<Rc<BrowserWindow>> {
+        BrowserWindow::of(&self.app()?.active_window()?)
+    }
+
+    // Pages asked for from outside: the command line, a second launch or the
+    // desktop. They go to the front window unless a new one is wanted.
+    pub fn open_urls(self: &Rc<Self>, urls: &[String], new_window: bool, incognito: bool) {
+        let existing = if new_window { None } else { self.active_window().or_else(|| self.windows.borrow().last().cloned()) };
+        let window = existing.unwrap_or_else(|| self.new_window());
+        if urls.is_empty() {
+            let homepage = self.state.borrow().homepage.clone();
+            window.create_tab(&homepage, incognito);
+        }
+        for url in urls {
+            let target = window::resolve_input(url, &self.state.borrow());
+            window.create_tab(&target, incognito);
+        }
+        window.present();
+    }
+
+    // Moves tabs, history and all, into a fresh window.
+    pub fn move_to_new_window(self: &Rc<Self>, tabs: &[Widget], group: Option<TabGroup>) {
+        if tabs.is_empty() { return; }
+        let target = self.new_window();
+        // Before the tabs arrive, so they keep their membership.
+        if let Some(group) = group { target.groups().restore(vec![group]); }
+        for page in tabs {
+            if let Some(source) = BrowserWindow::of(page) { source.release_tab(page); }
+            target.adopt_tab(page);
+        }
+        target.groups().refresh();
+        target.present();
+        self.save_session();
+    }
+
+    // Only the last window to close saves the full session and clears data;
+    // closing any other window just drops its tabs.
+    pub fn window_closing(&self, window: &Rc<BrowserWindow>) -> glib::signal::Inhibit {
+        let last = self.windows.borrow().iter().all(|w| Rc::ptr_eq(w, window));
+        if !last {
+            self.windows.borrow_mut().retain(|w| !Rc::ptr_eq(w, window));
+            self.save_session();
+            return glib::signal::Inhibit(false);
+        }
+        if !self.exit_cleared.get() { self.save_session(); }
+        self.history.borrow_mut().flush();
+
+        // --- CLEAR ON EXIT ---
+        // Wiping is async, so hold the close until WebKit reports back.
+        if self.exit_cleared.get() || is_amnesia_session() || !self.state.borrow().clear_on_exit {
+            self.windows.borrow_mut().clear();
+            return glib::signal::Inhibit(false);
+        }
+        self.exit_cleared.set(true);
+        window.window().set_visible(false);
+        let win_weak = window.window().downgrade();
+        clear_data::clear_all(&self.session, move |_| {
+            if let Some(win) = win_weak.upgrade() { win.close(); }
+        });
+        glib::signal::Inhibit(true)
+    }
+
+    // --- SESSION ---
+    // Pinned tabs are always restored; everything else only if the user asked.
+    // Returns whether any window came back.
+    pub fn restore_session(self: &Rc<Self>) -> bool {
+        let restore_all = self.state.borrow().restore_session;
+        for saved in Session::load().windows {
+            let (tabs, groups, active) = saved.startup_tabs(restore_all);
+            if tabs.is_empty() { continue; }
+            let window = self.new_window();
+            window.restore(&tabs, groups, active);
+            window.present();
+        }
+        !self.windows.borrow().is_empty()
+    }
+
+    pub fn save_session(&self) {
+        let windows = self.windows.borrow().iter().map(|w| w.capture()).filter(|w| !w.tabs.is_empty()).collect();
+        Session { windows }.save();
+    }
+
+    // --- SETTINGS ---
+    pub fn notebooks(&self) -> Vec<Notebook> {
+        self.windows.borrow().iter().map(|w| w.notebook().clone()).collect()
+    }
+
+    pub fn apply_cookies(&self) {
+        site_data::apply_cookie_settings(&self.state.borrow(), &self.session, &self.cookie_filter, &self.notebooks());
+    }
+
+    // Toolbar and tab strip settings take effect in every open window.
+    pub fn refresh_windows(&self) {
+        for window in self.windows.borrow().iter() { window.apply_settings(); }
+    }
+
+    pub fn settings_changed(&self) {
+        self.refresh_windows();
+        self.apply_cookies();
+    }
+
+    // --- TABS ---
+    // A new tab's webview, already inside its page.
+    pub fn create_webview(self: &Rc<Self>, url: &str, is_incognito: bool) -> WebView {
+        let (page, header) = self.new_page(is_incognito);
+        let webview = self.build_webview(&page, &header, None);
+        // Empty for duplicated/reopened tabs, which restore their own history.
+        if !url.is_empty() { webview.load_uri(url); }
+        webview
+    }
+
+    // A tab that only knows where it was; it loads when first selected (see wake).
+    pub fn create_lazy_tab(self: &Rc<Self>, url: &str, title: &str, is_incognito: bool) -> gtk::Box {
+        let (page, header) = self.new_page(is_incognito);
+        // Icon first, so the placeholder page shows it too.
+        header.show_placeholder(url, title);
+        header.load_favicon(&self.session);
+        let placeholder = tab_page::Placeholder { url: url.to_string(), title: title.to_string(), state: None, scroll: None };
+        tab_page::set_placeholder(&page, placeholder, "This tab will load when you select it.");
+        page
+    }
+
+    // The parts of a tab that outlive its webviews: page, label and menu.
+    fn new_page(self: &Rc<Self>, is_incognito: bool) -> (gtk::Box, Rc<TabLabel>) {
+        let header = TabLabel::new(is_incognito);
+        let page = tab_page::new();
+        header.attach_page(&page);
+        self.tab_menu.attach(page.upcast_ref(), &header);
+        (page, header)
+    }
+
+    // Gives a tab without a webview (see tab_page) a new one, back where the
+    // old one was: same history entry and, once loaded, the same scroll offset.
+    pub fn wake(self: &Rc<Self>, tab: &impl IsA<Widget>) -> Option<WebView> {
+        let page = tab_page::page(tab)?;
+        let (placeholder, header) = (tab_page::placeholder(&page)?, tab_label::header(&page)?);
+        let webview = self.build_webview(&page, &header, None);
+        tab_menu::restore_state(&webview, placeholder.state.as_ref(), &placeholder.url);
+        if let Some((x, y)) = placeholder.scroll { tab_page::scroll_after_load(&webview, x, y); }
+        Some(webview)
+    }
+
+    // A configured webview, placed in its tab's page. Handlers that touch
+    // window chrome look the window up on each signal, since tabs can be
+    // dragged from one window to another.
+    fn build_webview(self: &Rc<Self>, page: &gtk::Box, tab_header: &Rc<TabLabel>, related: Option<&WebView>) -> WebView {
+        let is_incognito = tab_header.is_incognito();
+        let user_manager = UserContentManager::new();
+
+        if self.state.borrow().enable_adblock {
+            let adblock_css = "iframe[src*='ads'], div[class*='ad-'], div[id*='google_ads'], .adsbygoogle, .ad-banner { display: none !important; }";
+            let style = UserStyleSheet::new(adblock_css, UserContentInjectedFrames::AllFrames, UserStyleLevel::User, &[], &[]);
+            user_manager.add_style_sheet(&style);
+        }
+        if let Some(filter) = self.cookie_filter.borrow().as_ref() {
+            user_manager.add_filter(filter);
+        }
+
+        let webview: WebView;
+        if let Some(opener) = related {
+            // Same web process, context and session as the opener, so window.opener works.
+            webview = glib::Object::builder().property("related-view", opener).property("user-content-manager", &user_manager).build();
+        } else if is_incognito {
+            let ephemeral_ctx = WebContext::new();
+            self.internal.register(&ephemeral_ctx);
+            let ephemeral_session = NetworkSession::new_ephemeral();
+            favicons::enable(&ephemeral_session);
+            self.downloads.watch(&ephemeral_session);
+            ephemeral_session.cookie_manager().set_accept_policy(site_data::accept_policy(&self.state.borrow()));
+            webview = glib::Object::builder().property("web-context", &ephemeral_ctx).property("network-session", &ephemeral_session).property("user-content-manager", &user_manager).build();
+        } else {
+            webview = glib::Object::builder().property("web-context", &self.context).property("network-session", self.session.as_ref()).property("user-content-manager", &user_manager).build();
+        }
+
+        let use_accel = self.state.borrow().use_hw_accel;
+
+        if let Some(settings) = WebViewExt::settings(&webview) {
+            if use_accel {
+                settings.set_hardware_acceleration_policy(HardwareAccelerationPolicy::Always);
+                settings.set_enable_webgl(true);
+            } else {
+                settings.set_hardware_acceleration_policy(HardwareAccelerationPolicy::Never);
+                settings.set_enable_webgl(false);
+            }
+            settings.set_enable_media_stream(true);
+            settings.set_enable_mediasource(true);
+            settings.set_enable_smooth_scrolling(false);
+            settings.set_enable_developer_extras(true);
+            settings.set_user_agent(Some("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36"));
+        }
+
+        self.internal.attach(&user_manager, &webview);
+
+        tab_header.attach(&webview);
+        tab_page::set_webview(page, &webview);
+
+        // Middle- and Ctrl-clicked links become tabs before any other policy
+        // handler sees them; each tab then runs the policies on its own load.
+        webview.connect_decide_policy(move |wv, decision, decision_type| {
+            if !matches!(decision_type, PolicyDecisionType::NavigationAction | PolicyDecisionType::NewWindowAction) { return false; }
+            let mut action = match decision.downcast_ref::<NavigationPolicyDecision>().and_then(|nav| nav.navigation_action()) { Some(a) => a, None => return false };
+            if action.navigation_type() != NavigationType::LinkClicked { return false; }
+            let background = match new_tab_click(action.mouse_button(), gdk::ModifierType::from_bits_truncate(action.modifiers())) { Some(b) => b, None => return false };
+            let target = match action.request().and_then(|r| r.uri()) { Some(u) => u.to_string(), None => return false };
+            decision.ignore();
+            if let Some(window) = BrowserWindow::for_new_tabs(wv) { window.open_tab(&target, is_incognito, background); }
+            true
+        });
+
+        // A pinned tab stays on its site; links elsewhere open beside it. Ahead
+        // of HTTPS-only, which would otherwise upgrade the link in place.
+        webview.connect_decide_policy(move |wv, decision, decision_type| {
+            if decision_type != PolicyDecisionType::NavigationAction || !tab_label::header(wv).map_or(false, |h| h.is_pinned()) { return false; }
+            let mut action = match decision.downcast_ref::<NavigationPolicyDecision>().and_then(|nav| nav.navigation_action()) { Some(a) => a, None => return false };
+            if action.navigation_type() != NavigationType::LinkClicked { return false; }
+            let target = match action.request().and_then(|r| r.uri()) { Some(u) => u.to_string(), None => return false };
+            if !tab_label::leaves_pinned_site(wv.uri().as_deref(), &target) { return false; }
+            decision.ignore();
+            if let Some(window) = BrowserWindow::for_new_tabs(wv) { window.open_tab(&target, is_incognito, false); }
+            true
+        });
+
+        // Then HTTPS-only: an upgraded navigation must not reach later policy handlers.
+        https_only::attach(&webview, self.state.clone(), self.https_exceptions.clone(), is_incognito);
+        tls::attach(&webview);
+        self.error_pages.attach(&webview, self.state.clone());
+
+        // Last: use_with_policies is itself the decision to go ahead. Every
+        // handler above that claims a navigation ignores it and starts a new
+        // one (load_uri, or a load in another tab), which comes back through
+        // here, so no page loads without its site's autoplay policy.
+        let perms_policy = self.permissions.clone();
+        webview.connect_decide_policy(move |_, decision, decision_type| {
+            if decision_type != PolicyDecisionType::NavigationAction { return false; }
+            let uri = match navigation_uri(decision) { Some(u) => u, None => return false };
+            if let Some(policies) = permissions::autoplay_policies(&perms_policy.borrow(), &uri) {
+                decision.use_with_policies(&policies);
+                return true;
+            }
+            false
+        });
+
+        page_menu::attach(&webview, Rc::downgrade(self), is_incognito);
+
+        // --- PERMISSIONS ---
+        let perms_request = self.permissions.clone();
+        webview.connect_permission_request(move |wv, request| {
+            match BrowserWindow::prompts_for(wv) {
+                Some(prompts) => permissions::handle_request(wv, request, &perms_request, &prompts, !is_incognito),
+                None => false,
+            }
+        });
+
+        // Incognito decisions aren't kept, and autoplay can only change with
+        // a saved one, so private tabs aren't asked.
+        if !is_incognito {
+            let (perms_autoplay, wv_autoplay) = (self.permissions.clone(), webview.downgrade());
+            permissions::watch_autoplay(&user_manager, move || {
+                let wv = match wv_autoplay.upgrade() { Some(w) => w, None => return };
+                if let Some(prompts) = BrowserWindow::prompts_for(&wv) { permissions::offer_autoplay(&wv, &perms_autoplay, &prompts); }
+            });
+        }
+
+        let notify_show = self.notifications.clone();
+        webview.connect_show_notification(move |wv, notification| notify_show.show(wv, notification));
+
+        let history_load = self.history.clone();
+        webview.connect_load_changed(move |wv, event| {
+            if event == LoadEvent::Started {
+                if let Some(prompts) = BrowserWindow::prompts_for(wv) { prompts.dismiss(wv); }
+            }
+            if let Some(window) = BrowserWindow::of(wv) { window.show_page_icons(wv); }
+            if event == LoadEvent::Finished && !is_incognito {
+                if let Some(uri) = wv.uri().filter(|u| history::should_record(u)) {
+                    let title = wv.title().map(|t| t.to_string()).unwrap_or_default();
+                    history_load.borrow_mut().record_visit(&uri, &title);
+                }
+            }
+        });
+
+        // The letter fallback follows the uri until the page's favicon arrives.
+        webview.connect_uri_notify(move |wv| {
+            if let Some(window) = BrowserWindow::of(wv) { window.show_uri(wv); }
+        });
+        webview.connect_favicon_notify(move |wv| {
+            if let Some(window) = BrowserWindow::of(wv) { window.show_site_icon(wv); }
+        });
+
+        let history_title = self.history.clone();
+        webview.connect_title_notify(move |wv| {
+            if let (false, Some(uri), Some(title)) = (is_incognito, wv.uri(), wv.title()) { history_title.borrow_mut().set_title(&uri, &title); }
+            if let Some(window) = BrowserWindow::of(wv) { window.show_title(wv); }
+        });
+
+        webview.connect_estimated_load_progress_notify(move |wv| {
+            if let Some(window) = BrowserWindow::of(wv) { window.show_progress(wv); }
+        });
+
+        // --- POPUPS ---
+        let weak = Rc::downgrade(self);
+        webview.connect_create(move |wv, action| {
+            let browser = weak.upgrade()?;
+            browser.create_related(wv, action, is_incognito).map(|related| related.upcast())
+        });
+
+        // window.close() from the page.
+        webview.connect_close(|wv| {
+            if let Some(window) = BrowserWindow::of(wv) {
+                window.release_tab(wv);
+            } else if let Some(popup) = wv.root().and_then(|r| r.downcast::<Window>().ok()) {
+                popup.close();
+            }
+        });
+
+        // --- CRASHES ---
+        // The dead webview makes way for a placeholder with Reload. A tab that
+        // crashed in the background reloads by itself when it's selected.
+        let crashes = self.crashes.clone();
+        webview.connect_web_process_terminated(move |wv, reason| {
+            let (url, title) = tab_label::header(wv).map(|h| (h.uri(), h.title())).unwrap_or_default();
+            let (logged_url, logged_title) = if is_incognito { ("", "") } else { (url.as_str(), title.as_str()) };
+            crashes.borrow_mut().record(logged_url, logged_title, reason);
+            let message = format!("{}.", crashes::reason_label(reason));
+            // Not from inside the webview's own signal.
+            let wv = wv.downgrade();
+            glib::idle_add_local_once(move || {
+                let webview = match wv.upgrade() { Some(w) => w, None => return };
+                let page = match tab_page::page(&webview) { Some(p) => p, None => return };
+                if let Some(prompts) = BrowserWindow::prompts_for(&page) { prompts.dismiss(&webview); }
+                let placeholder = tab_page::Placeholder { url, title, state: Some(webview.session_state()), scroll: None };
+                tab_page::set_placeholder(&page, placeholder, &message);
+            });
+        });
+
+        webview
+    }
+
+    // target=_blank links and window.open(). Blocked unless the user clicked
+    // something or the site is allowed; the view is placed once WebKit says
+    // it's ready, when the requested window features are known. A pop-up
+    // blocked by the default rule is still created, so window.opener and
+    // anything the opener writes into it survive, but it's only placed if
+    // the user allows it; otherwise it's dropped with the prompt.
+    fn create_related(self: &Rc<Self>, opener: &WebView, action: &NavigationAction, is_incognito: bool) -> Option<WebView> {
+        let mut action = action.clone();
+        let opener_uri = opener.uri().map(|u| u.to_string());
+        let user_gesture = action.is_user_gesture();
+        let decided = opener_uri.as_deref().and_then(permissions::origin_of)
+            .and_then(|o| self.permissions.borrow().get(&o, PermissionKind::Popups)).is_some();
+        let blocked = !permissions::popup_allowed(&self.permissions.borrow(), opener_uri.as_deref(), user_gesture);
+        let prompts = BrowserWindow::prompts_for(opener);
+        if blocked && (decided || prompts.is_none()) { return None; }
+
+        let (page, header) = self.new_page(is_incognito);
+        let related = self.build_webview(&page, &header, Some(opener));
+        // Nothing else holds the page until it's placed (or refused).
+        let unplaced = Rc::new(RefCell::new(Some(page)));
+        let ready = Rc::new(Cell::new(false));
+        let allowed = Rc::new(Cell::new(!blocked));
+        let weak = Rc::downgrade(self);
+        let opener_weak = opener.downgrade();
+        let unplaced_show = unplaced.clone();
+        let show = Rc::new(move |wv: &WebView| {
+            let page = unplaced_show.borrow_mut().take();
+            if let (Some(_), Some(browser)) = (page, weak.upgrade()) { browser.show_related(wv, opener_weak.upgrade()); }
+        });
+
+        let (ready_show, allowed_show, show_ready) = (ready.clone(), allowed.clone(), show.clone());
+        related.connect_ready_to_show(move |wv| {
+            ready_show.set(true);
+            if allowed_show.get() { show_ready(wv); }
+        });
+        if let (true, Some(prompts)) = (blocked, prompts) {
+            let pending = related.clone();
+            permissions::offer_blocked_popup(opener, &self.permissions, &prompts, !is_incognito, move |allow| {
+                if !allow { unplaced.borrow_mut().take(); return; }
+                allowed.set(true);
+                if ready.get() { show(&pending); }
+            });
+        }
+        Some(related)
+    }
+
+    // Pages that asked for a bare window (no toolbar or location bar) get a
+    // small one of their own; everything else opens as a tab beside the opener.
+    fn show_related(self: &Rc<Self>, webview: &WebView, opener: Option<WebView>) {
+        let features = webview.window_properties();
+        let is_popup = features.as_ref().map_or(false, |f| !f.is_toolbar_visible() && !f.is_locationbar_visible());
+        if !is_popup {
+            let window = opener.as_ref().and_then(BrowserWindow::for_new_tabs)
+                .or_else(|| self.active_window())
+                .unwrap_or_else(|| self.new_window());
+            window.insert_tab(webview, false);
+            window.present();
+            return;
+        }
+
+        let (width, height) = popup_size(features.map(|f| f.geometry()).map(|g| (g.width(), g.height())));
+        let page = match tab_page::page(webview) { Some(p) => p, None => return };
+        let popup = Window::builder().title("Rusty Browser").default_width(width).default_height(height).build();
+        let opener_window = opener.as_ref().and_then(BrowserWindow::for_new_tabs).or_else(|| self.active_window());
+        BrowserWindow::host_popup(&popup, &page, opener_window.as_ref());
+        popup.set_application(self.app().as_ref());
+        let popup_weak = popup.downgrade();
+        webview.connect_title_notify(move |wv| {
+            if let (Some(popup), Some(title)) = (popup_weak.upgrade(), wv.title()) { popup.set_title(Some(&format!("{} - Rusty Browser", title))); }
+        });
+        popup.present();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{new_tab_click, popup_size, POPUP_SIZE};
+    use gtk::gdk::{self, ModifierType};
+
+    #[test]
+    fn pop_ups_get_the_size_they_ask_for() {
+        assert_eq!(popup_size(Some((400, 300))), (400, 300));
+        assert_eq!(popup_size(None), POPUP_SIZE);
+    }
+
+    #[test]
+    fn missing_sides_fall_back_to_the_default() {
+        assert_eq!(popup_size(Some((0, 300))), (POPUP_SIZE.0, 300));
+        assert_eq!(popup_size(Some((400, 0))), (400, POPUP_SIZE.1));
+        assert_eq!(popup_size(Some((-1, -1))), POPUP_SIZE);
+    }
+
+    #[test]
+    fn middle_and_ctrl_clicks_open_background_tabs() {
+        assert_eq!(new_tab_click(gdk::BUTTON_MIDDLE, ModifierType::empty()), Some(true));
+        assert_eq!(new_tab_click(gdk::BUTTON_PRIMARY, ModifierType::CONTROL_MASK), Some(true));
+    }
+
+    #[test]
+    fn shift_brings_the_new_tab_forward() {
+        assert_eq!(new_tab_click(gdk::BUTTON_MIDDLE, ModifierType::SHIFT_MASK), Some(false));
+        assert_eq!(new_tab_click(gdk::BUTTON_PRIMARY, ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK), Some(false));
+    }
+
+    #[test]
+    fn plain_clicks_stay_in_the_tab() {
+        assert_eq!(new_tab_click(gdk::BUTTON_PRIMARY, ModifierType::empty()), None);
+        assert_eq!(new_tab_click(gdk::BUTTON_PRIMARY, ModifierType::SHIFT_MASK), None);
+    }
+}
+
@@ -0,0 +1,170 @@
+// --- CLEAR BROWSING DATA ---
+use gtk::prelude::*;
+use gtk::{gio, glib, Box, CheckButton, Dialog, DropDown, Entry, Label, Orientation, ResponseType, StringList, Window};
+use webkit6::prelude::*;
+use webkit6::{NetworkSession, WebsiteData, WebsiteDataTypes};
+
+const TIME_RANGES: [&str; 4] = ["Last Hour", "Last Day", "Last Week", "All Time"];
+
+pub fn categories() -> Vec<(&'static str, WebsiteDataTypes)> {
+    vec![
+        ("Cookies", WebsiteDataTypes::COOKIES),
+        ("Cached Images and Files", WebsiteDataTypes::DISK_CACHE | WebsiteDataTypes::MEMORY_CACHE),
+        ("Local Storage", WebsiteDataTypes::LOCAL_STORAGE | WebsiteDataTypes::SESSION_STORAGE),
+        ("IndexedDB Databases", WebsiteDataTypes::INDEXEDDB_DATABASES),
+        ("Service Workers", WebsiteDataTypes::SERVICE_WORKER_REGISTRATIONS | WebsiteDataTypes::DOM_CACHE),
+        ("HSTS Policies", WebsiteDataTypes::HSTS_CACHE),
+    ]
+}
+
+// A timespan of zero means "everything" to WebKit.
+fn timespan_for(index: u32) -> glib::TimeSpan {
+    match index {
+        0 => glib::TimeSpan::from_hours(1),
+        1 => glib::TimeSpan::from_days(1),
+        2 => glib::TimeSpan::from_days(7),
+        _ => glib::TimeSpan::from_seconds(0),
+    }
+}
+
+// Accepts a host ("www.example.com") or a full URL and returns the bare host.
+pub fn normalize_domain(input: &str) -> String {
+    let input = input.trim().to_lowercase();
+    let without_scheme = input.split("://").last().unwrap_or("");
+    let host = without_scheme.split(['/', '?', '#']).next().unwrap_or("");
+    let host = host.rsplit('@').next().unwrap_or("");
+    host.split(':').next().unwrap_or("").to_string()
+}
+
+// WebKit keeps website data per registrable domain ("example.com"), so a
+// host matches the record for its own site, plus any host-level records
+// (e.g. HSTS) under it. Clearing "mail.example.com" therefore clears
+// everything stored for "example.com".
+pub fn domain_matches(record: &str, domain: &str) -> bool {
+    record == domain || domain.ends_with(&format!(".{}", record)) || record.ends_with(&format!(".{}", domain))
+}
+
+pub fn clear_all(session: &NetworkSession, done: impl FnOnce(Result<(), glib::Error>) + 'static) {
+    session.website_data_manager().clear(WebsiteDataTypes::ALL, glib::TimeSpan::from_seconds(0), gio::Cancellable::NONE, done);
+}
+
+// `done` hears false when nothing was stored for the domain.
+pub fn clear_domain(session: &NetworkSession, types: WebsiteDataTypes, domain: &str, done: impl FnOnce(Result<bool, glib::Error>) + 'static) {
+    let manager = session.website_data_manager();
+    let manager_remove = manager.clone();
+    let domain = domain.to_string();
+    manager.fetch(types, gio::Cancellable::NONE, move |result| match result {
+        Ok(records) => {
+            let matching: Vec<WebsiteData> = records
+                .into_iter()
+                .filter(|r| r.name().map_or(false, |n| domain_matches(&n, &domain)))
+                .collect();
+            if matching.is_empty() { done(Ok(false)); }
+            else { manager_remove.remove(types, &matching, gio::Cancellable::NONE, move |result| done(result.map(|_| true))); }
+        }
+        Err(e) => done(Err(e)),
+    });
+}
+
+pub fn show_clear_data_dialog(parent: &impl IsA<Window>, session: &NetworkSession) {
+    let dialog = Dialog::builder().transient_for(parent).modal(true).title("Clear Browsing Data").build();
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Clear Data", ResponseType::Accept);
+    let content_area = dialog.content_area();
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_top(20); vbox.set_margin_bottom(20); vbox.set_margin_start(20); vbox.set_margin_end(20);
+
+    let label_range = Label::new(None); label_range.set_markup("<b>Time Range</b>"); label_range.set_halign(gtk::Align::Start); vbox.append(&label_range);
+    let range_dropdown = DropDown::new(Some(StringList::new(&TIME_RANGES)), Option::<gtk::Expression>::None);
+    range_dropdown.set_selected(1);
+    vbox.append(&range_dropdown);
+
+    let label_site = Label::new(None); label_site.set_markup("<b>Only This Site</b>"); label_site.set_halign(gtk::Align::Start); vbox.append(&label_site);
+    let domain_entry = Entry::new(); domain_entry.set_placeholder_text(Some("example.com (leave empty for all sites)")); vbox.append(&domain_entry);
+    let site_lbl = Label::new(None); site_lbl.set_markup("<i>(A single site is cleared for all time, along with the rest of its domain)</i>"); site_lbl.set_halign(gtk::Align::Start); site_lbl.set_sensitive(false); vbox.append(&site_lbl);
+
+    let range_weak = range_dropdown.downgrade();
+    domain_entry.connect_changed(move |entry| {
+        if let Some(range) = range_weak.upgrade() { range.set_sensitive(entry.text().trim().is_empty()); }
+    });
+
+    let label_types = Label::new(None); label_types.set_markup("<b>Categories</b>"); label_types.set_halign(gtk::Align::Start); vbox.append(&label_types);
+    let checks: Vec<(CheckButton, WebsiteDataTypes)> = categories()
+        .into_iter()
+        .map(|(name, types)| {
+            let check = CheckButton::with_label(name);
+            check.set_active(true);
+            vbox.append(&check);
+            (check, types)
+        })
+        .collect();
+
+    let status_lbl = Label::new(None); status_lbl.set_halign(gtk::Align::Start); status_lbl.set_wrap(true); vbox.append(&status_lbl);
+    content_area.append(&vbox);
+
+    let session = session.clone();
+    dialog.connect_response(move |d, response| {
+        if response != ResponseType::Accept { d.close(); return; }
+
+        let mut types = WebsiteDataTypes::empty();
+        for (check, kinds) in &checks { if check.is_active() { types |= *kinds; } }
+        if types.is_empty() { status_lbl.set_text("Select at least one category."); return; }
+
+        d.set_sensitive(false);
+        status_lbl.set_text("Clearing…");
+        let dialog_weak = d.downgrade();
+        let status_weak = status_lbl.downgrade();
+        let domain = normalize_domain(&domain_entry.text());
+        let nothing = format!("Nothing is stored for {}.", domain);
+        let done = move |result: Result<bool, glib::Error>| {
+            let dialog = match dialog_weak.upgrade() { Some(d) => d, None => return };
+            let message = match result {
+                Ok(true) => return dialog.close(),
+                Ok(false) => nothing,
+                Err(e) => format!("Could not clear data: {}", e),
+            };
+            dialog.set_sensitive(true);
+            if let Some(status) = status_weak.upgrade() { status.set_text(&message); }
+        };
+
+        if domain.is_empty() {
+            session.website_data_manager().clear(types, timespan_for(range_dropdown.selected()), gio::Cancellable::NONE, move |result| done(result.map(|_| true)));
+        } else {
+            clear_domain(&session, types, &domain, done);
+        }
+    });
+    dialog.show();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_host_matches_its_sites_record() {
+        assert!(domain_matches("example.com", "example.com"));
+        assert!(domain_matches("example.com", "mail.example.com"));
+        assert!(domain_matches("example.com", "www.example.com"));
+    }
+
+    #[test]
+    fn a_domain_matches_host_level_records_under_it() {
+        assert!(domain_matches("mail.example.com", "example.com"));
+        assert!(domain_matches("a.b.example.com", "example.com"));
+    }
+
+    #[test]
+    fn lookalikes_and_siblings_do_not_match() {
+        assert!(!domain_matches("badexample.com", "example.com"));
+        assert!(!domain_matches("example.com.evil", "example.com"));
+        assert!(!domain_matches("other.com", "example.com"));
+        assert!(!domain_matches("news.example.com", "mail.example.com"));
+    }
+
+    #[test]
+    fn normalizes_urls_to_hosts() {
+        assert_eq!(normalize_domain("https://www.Example.com:8080/path?q#x"), "www.example.com");
+        assert_eq!(normalize_domain("user@mail.example.com"), "mail.example.com");
+        assert_eq!(normalize_domain("  example.com/  "), "example.com");
+    }
+}
@@ -0,0 +1,102 @@
+// --- CRASH LOG ---
+use gtk::glib;
+use serde::{Serialize, Deserialize};
+use webkit6::WebProcessTerminationReason;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{load_json, save_json};
+
+const CRASHES_FILE: &str = "crashes.json";
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CrashEntry {
+    pub url: String,
+    pub title: String,
+    pub reason: String,
+    pub time: i64,
+}
+
+// Newest first, shown on rusty:crashes. Incognito crashes are logged without
+// their address.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct CrashLog {
+    entries: Vec<CrashEntry>,
+}
+
+pub type SharedCrashLog = Rc<RefCell<CrashLog>>;
+
+// The one wording for a dead page: the tab's tooltip, its placeholder and the log.
+pub fn reason_label(reason: WebProcessTerminationReason) -> &'static str {
+    match reason {
+        WebProcessTerminationReason::ExceededMemoryLimit => "This page ran out of memory",
+        WebProcessTerminationReason::TerminatedByApi => "This page was stopped",
+        _ => "This page crashed",
+    }
+}
+
+impl CrashLog {
+    pub fn load() -> Self {
+        load_json(CRASHES_FILE)
+    }
+
+    fn save(&self) {
+        save_json(CRASHES_FILE, self);
+    }
+
+    pub fn entries(&self) -> &[CrashEntry] {
+        &self.entries
+    }
+
+    pub fn record(&mut self, url: &str, title: &str, reason: WebProcessTerminationReason) {
+        let time = glib::real_time() / 1_000_000;
+        self.push(CrashEntry { url: url.to_string(), title: title.to_string(), reason: reason_label(reason).to_string(), time });
+        self.save();
+    }
+
+    fn push(&mut self, entry: CrashEntry) {
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reason_label, CrashEntry, CrashLog, MAX_ENTRIES};
+    use webkit6::WebProcessTerminationReason;
+
+    fn entry(time: i64) -> CrashEntry {
+        CrashEntry { url: "https://example.com/".to_string(), title: String::new(), reason: "This page crashed".to_string(), time }
+    }
+
+    #[test]
+    fn newest_crash_comes_first() {
+        let mut log = CrashLog::default();
+        log.push(entry(1));
+        log.push(entry(2));
+        let times: Vec<i64> = log.entries().iter().map(|e| e.time).collect();
+        assert_eq!(times, [2, 1]);
+    }
+
+    #[test]
+    fn the_log_keeps_only_recent_crashes() {
+        let mut log = CrashLog::default();
+        for time in 0..MAX_ENTRIES as i64 + 5 { log.push(entry(time)); }
+        assert_eq!(log.entries().len(), MAX_ENTRIES);
+        assert_eq!(log.entries()[0].time, MAX_ENTRIES as i64 + 4);
+        assert_eq!(log.entries()[MAX_ENTRIES - 1].time, 5);
+    }
+
+    #[test]
+    fn reasons_are_worded_for_people() {
+        assert_eq!(reason_label(WebProcessTerminationReason::ExceededMemoryLimit), "This page ran out of memory");
+        assert_eq!(reason_label(WebProcessTerminationReason::TerminatedByApi), "This page was stopped");
+        assert_eq!(reason_label(WebProcessTerminationReason::Crashed), "This page crashed");
+    }
+}
@@ -0,0 +1,83 @@
+// --- DEFAULT BROWSER ---
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use std::ffi::OsStr;
+use std::fs;
+
+use crate::is_amnesia_session;
+
+pub const DESKTOP_FILE: &str = "rusty_browser.desktop";
+const ICON_NAME: &str = "rusty-browser";
+
+fn exec_path() -> String {
+    let exe = std::env::current_exe().map(|p| p.to_string_lossy().into_owned()).unwrap_or_else(|_| "rusty_browser".to_string());
+    // Desktop entry quoting: only needed when the path has spaces or quotes.
+    if exe.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", exe.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        exe
+    }
+}
+
+// Also what `--desktop-entry` prints for install.sh.
+pub fn desktop_entry() -> String {
+    let exec = exec_path();
+    format!("[Desktop Entry]
+Name=Rusty Browser
+GenericName=Web Browser
+Comment=A privacy-focused, high-performance web browser built with Rust and WebKit.
+Exec={exec} %U
+Icon={ICON_NAME}
+Type=Application
+Categories=Network;WebBrowser;
+StartupNotify=true
+MimeType=text/html;text/xml;application/xhtml+xml;x-scheme-handler/http;x-scheme-handler/https;
+Actions=new-window;new-private-window;
+
+[Desktop Action new-window]
+Name=New Window
+Exec={exec} --new-window
+
+[Desktop Action new-private-window]
+Name=New Private Window
+Exec={exec} --new-window --incognito
+")
+}
+
+// xdg-settings can only point at an entry the desktop knows about, so a
+// browser run straight from the build directory installs one for the user.
+fn ensure_desktop_entry() {
+    if gio::DesktopAppInfo::new(DESKTOP_FILE).is_some() { return; }
+    let mut dir = glib::user_data_dir();
+    dir.push("applications");
+    if fs::create_dir_all(&dir).is_err() { return; }
+    let _ = fs::write(dir.join(DESKTOP_FILE), desktop_entry());
+    let _ = gio::Subprocess::newv(&[OsStr::new("update-desktop-database"), dir.as_os_str()], gio::SubprocessFlags::STDERR_SILENCE);
+}
+
+fn xdg_settings(args: &[&str]) -> Option<gio::Subprocess> {
+    let mut argv: Vec<&OsStr> = vec![OsStr::new("xdg-settings")];
+    argv.extend(args.iter().map(OsStr::new));
+    gio::Subprocess::newv(&argv, gio::SubprocessFlags::STDOUT_PIPE | gio::SubprocessFlags::STDERR_SILENCE).ok()
+}
+
+// None when xdg-settings is missing or can't tell (e.g. an unsupported desktop).
+pub fn check(done: impl FnOnce(Option<bool>) + 'static) {
+    let process = match xdg_settings(&["check", "default-web-browser", DESKTOP_FILE]) { Some(p) => p, None => return done(None) };
+    process.communicate_utf8_async(None, gio::Cancellable::NONE, move |result| {
+        let answer = match result {
+            Ok((Some(out), _)) => match out.trim() { "yes" => Some(true), "no" => Some(false), _ => None },
+            _ => None,
+        };
+        done(answer);
+    });
+}
+
+// Both the desktop entry and xdg-settings' mimeapps.list are files in the
+// user's home, so an amnesia session can't do this.
+pub fn make_default(done: impl FnOnce(bool) + 'static) {
+    if is_amnesia_session() { return done(false); }
+    ensure_desktop_entry();
+    let process = match xdg_settings(&["set", "default-web-browser", DESKTOP_FILE]) { Some(p) => p, None => return done(false) };
+    process.wait_check_async(gio::Cancellable::NONE, move |result| done(result.is_ok()));
+}
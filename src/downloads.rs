@@ -0,0 +1,90 @@
+// --- DOWNLOADS ---
+use gtk::prelude::*;
+use gtk::gio;
+use webkit6::prelude::*;
+use webkit6::{Download, DownloadError, NetworkSession};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DownloadState {
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+pub struct DownloadEntry {
+    pub id: u64,
+    pub download: Download,
+    pub source: String,
+    pub state: Cell<DownloadState>,
+}
+
+impl DownloadEntry {
+    pub fn destination(&self) -> Option<String> {
+        self.download.destination().map(|d| d.to_string())
+    }
+}
+
+// Downloads of this session. WebKit picks the destination (the XDG download
+// folder); we only keep track of them for rusty:downloads. The page refers to
+// entries by id, since the list changes while it's open.
+#[derive(Default)]
+pub struct Downloads {
+    items: RefCell<Vec<Rc<DownloadEntry>>>,
+    next_id: Cell<u64>,
+}
+
+impl Downloads {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self::default())
+    }
+
+    pub fn watch(self: &Rc<Self>, session: &NetworkSession) {
+        let this = Rc::downgrade(self);
+        session.connect_download_started(move |_, download| {
+            if let Some(this) = this.upgrade() { this.track(download); }
+        });
+    }
+
+    fn track(&self, download: &Download) {
+        let source = download.request().and_then(|r| r.uri()).map(|u| u.to_string()).unwrap_or_default();
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let entry = Rc::new(DownloadEntry { id, download: download.clone(), source, state: Cell::new(DownloadState::Running) });
+
+        let failed_entry = Rc::downgrade(&entry);
+        download.connect_failed(move |_, error| {
+            if let Some(e) = failed_entry.upgrade() {
+                e.state.set(if error.matches(DownloadError::CancelledByUser) { DownloadState::Cancelled } else { DownloadState::Failed });
+            }
+        });
+        // "finished" also fires after "failed", so only promote running ones.
+        let finished_entry = Rc::downgrade(&entry);
+        download.connect_finished(move |_| {
+            if let Some(e) = finished_entry.upgrade() {
+                if e.state.get() == DownloadState::Running { e.state.set(DownloadState::Finished); }
+            }
+        });
+
+        self.items.borrow_mut().insert(0, entry);
+    }
+
+    pub fn items(&self) -> Vec<Rc<DownloadEntry>> {
+        self.items.borrow().clone()
+    }
+
+    pub fn get(&self, id: u64) -> Option<Rc<DownloadEntry>> {
+        self.items.borrow().iter().find(|e| e.id == id).cloned()
+    }
+
+    pub fn clear_inactive(&self) {
+        self.items.borrow_mut().retain(|e| e.state.get() == DownloadState::Running);
+    }
+}
+
+pub fn open_path(path: &str) {
+    let uri = gio::File::for_path(path).uri();
+    let _ = gio::AppInfo::launch_default_for_uri(&uri, gio::AppLaunchContext::NONE);
+}
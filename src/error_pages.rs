@@ -0,0 +1,109 @@
+// --- NETWORK ERROR PAGES ---
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use webkit6::prelude::*;
+use webkit6::{NetworkError, PolicyError, WebView};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::pages;
+use crate::AppConfig;
+
+struct Failure {
+    title: &'static str,
+    detail: &'static str,
+    suggest_search: bool,
+}
+
+fn classify(error: &glib::Error) -> Failure {
+    let offline = !gio::NetworkMonitor::default().is_network_available();
+    if offline {
+        Failure { title: "You're offline", detail: "Check your network connection. The page will reload when you're back online.", suggest_search: false }
+    } else if error.matches(gio::ResolverError::NotFound) || error.matches(gio::ResolverError::TemporaryFailure) {
+        Failure { title: "Server not found", detail: "The address couldn't be resolved. Check it for typos.", suggest_search: true }
+    } else if error.matches(gio::IOErrorEnum::ConnectionRefused) {
+        Failure { title: "Connection refused", detail: "The server is reachable but isn't accepting connections. It may be down or restarting.", suggest_search: false }
+    } else if error.matches(gio::IOErrorEnum::TimedOut) {
+        Failure { title: "Connection timed out", detail: "The server took too long to respond.", suggest_search: false }
+    } else if error.matches(gio::IOErrorEnum::NetworkUnreachable) || error.matches(gio::IOErrorEnum::HostUnreachable) {
+        Failure { title: "Network unreachable", detail: "Your computer couldn't find a route to the server.", suggest_search: false }
+    } else {
+        Failure { title: "This page couldn't be loaded", detail: "Something went wrong while loading the page.", suggest_search: false }
+    }
+}
+
+// The code by name, for the domains a load usually fails in; worth quoting
+// in a bug report.
+fn error_code(error: &glib::Error) -> Option<String> {
+    if let Some(code) = error.kind::<NetworkError>() { return Some(format!("{:?}", code)); }
+    if let Some(code) = error.kind::<gio::ResolverError>() { return Some(format!("{:?}", code)); }
+    if let Some(code) = error.kind::<gio::TlsError>() { return Some(format!("{:?}", code)); }
+    error.kind::<gio::IOErrorEnum>().map(|code| format!("{:?}", code))
+}
+
+fn error_page_html(uri: &str, error: &glib::Error, search_engine: &str) -> String {
+    let failure = classify(error);
+    let host = glib::Uri::parse(uri, glib::UriFlags::NONE).ok().and_then(|u| u.host()).map(|h| h.to_string()).unwrap_or_default();
+    let search = if failure.suggest_search && !host.is_empty() {
+        let query = glib::Uri::escape_string(host.trim_start_matches("www."), None, false);
+        format!(r#"<a class="button secondary" href="{}{}">Search Instead</a>"#, glib::markup_escape_text(search_engine), query)
+    } else {
+        String::new()
+    };
+    pages::render(failure.title, &format!(r#"
+    <h1>{title}</h1>
+    <p>{detail}</p>
+    <p><code>{uri}</code></p>
+    <a class="button primary" href="{uri}">Try Again</a>
+    {search}
+    <p class="meta">{domain}{code}: {message}</p>"#,
+        title = failure.title,
+        detail = failure.detail,
+        uri = glib::markup_escape_text(uri),
+        search = search,
+        domain = glib::markup_escape_text(error.domain().as_str()),
+        code = error_code(error).map(|c| format!(" ({})", c)).unwrap_or_default(),
+        message = glib::markup_escape_text(error.message())))
+}
+
+// Tracks tabs sitting on an error page and reloads them once the network
+// comes back.
+pub struct ErrorPages {
+    failed: RefCell<Vec<(glib::WeakRef<WebView>, String)>>,
+}
+
+impl ErrorPages {
+    pub fn new() -> Rc<Self> {
+        let this = Rc::new(Self { failed: RefCell::new(Vec::new()) });
+        let weak = Rc::downgrade(&this);
+        gio::NetworkMonitor::default().connect_network_changed(move |_, available| {
+            if let (true, Some(this)) = (available, weak.upgrade()) { this.retry_all(); }
+        });
+        this
+    }
+
+    fn retry_all(&self) {
+        let failed: Vec<(glib::WeakRef<WebView>, String)> = self.failed.borrow_mut().drain(..).collect();
+        for (webview, uri) in failed {
+            // The error page keeps the failing URI, so a different URI means the user moved on.
+            if let Some(wv) = webview.upgrade() {
+                if wv.uri().as_deref() == Some(uri.as_str()) { wv.load_uri(&uri); }
+            }
+        }
+    }
+
+    pub fn attach(self: &Rc<Self>, webview: &WebView, state: Rc<RefCell<AppConfig>>) {
+        let this = Rc::downgrade(self);
+        webview.connect_load_failed(move |wv, _, failing_uri, error| {
+            // Stopped loads, downloads and our own ignored navigations aren't failures.
+            if error.matches(NetworkError::Cancelled) || error.is::<PolicyError>() { return false; }
+            wv.load_alternate_html(&error_page_html(failing_uri, error, &state.borrow().search_engine_url), failing_uri, None);
+            if let Some(this) = this.upgrade() {
+                let mut failed = this.failed.borrow_mut();
+                failed.retain(|(w, _)| w.upgrade().map_or(false, |w| &w != wv));
+                failed.push((wv.downgrade(), failing_uri.to_string()));
+            }
+            true
+        });
+    }
+}
@@ -0,0 +1,144 @@
+// --- FAVICONS ---
+use gtk::prelude::*;
+use gtk::{cairo, gdk, gio, glib, Image};
+use webkit6::prelude::*;
+use webkit6::{NetworkSession, WebView};
+
+const TAB_ICON_SIZE: i32 = 16;
+const LETTER_COLORS: [(f64, f64, f64); 6] = [
+    (0.24, 0.68, 0.91),
+    (0.35, 0.75, 0.45),
+    (0.91, 0.60, 0.24),
+    (0.75, 0.40, 0.85),
+    (0.91, 0.36, 0.40),
+    (0.30, 0.72, 0.70),
+];
+
+// Ephemeral sessions keep their favicon database in memory, so incognito
+// and amnesia tabs get icons without anything reaching the disk.
+pub fn enable(session: &NetworkSession) {
+    session.website_data_manager().set_favicons_enabled(true);
+}
+
+fn host_of(uri: &str) -> String {
+    glib::Uri::parse(uri, glib::UriFlags::NONE).ok().and_then(|u| u.host()).map(|h| h.trim_start_matches("www.").to_string()).unwrap_or_default()
+}
+
+pub fn letter_for(uri: &str) -> String {
+    host_of(uri).chars().find(|c| c.is_alphanumeric()).map(|c| c.to_uppercase().to_string()).unwrap_or_else(|| "?".to_string())
+}
+
+// Same site, same colour, wherever the letter shows up.
+fn color_for(uri: &str) -> (f64, f64, f64) {
+    let sum: usize = host_of(uri).bytes().map(|b| b as usize).sum();
+    LETTER_COLORS[sum % LETTER_COLORS.len()]
+}
+
+pub fn letter_texture(uri: &str, size: i32) -> Option<gdk::Texture> {
+    let mut surface = cairo::ImageSurface::create(cairo::Format::ARgb32, size, size).ok()?;
+    {
+        let cr = cairo::Context::new(&surface).ok()?;
+        let (r, g, b) = color_for(uri);
+        let half = size as f64 / 2.0;
+        cr.set_source_rgb(r, g, b);
+        cr.arc(half, half, half, 0.0, std::f64::consts::TAU);
+        cr.fill().ok()?;
+
+        let letter = letter_for(uri);
+        cr.set_source_rgb(0.12, 0.12, 0.13);
+        cr.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+        cr.set_font_size(size as f64 * 0.62);
+        let extents = cr.text_extents(&letter).ok()?;
+        cr.move_to(half - extents.width() / 2.0 - extents.x_bearing(), half - extents.height() / 2.0 - extents.y_bearing());
+        cr.show_text(&letter).ok()?;
+    }
+    surface.flush();
+    let stride = surface.stride() as usize;
+    let data = surface.data().ok()?.to_vec();
+    // Cairo's ARGB32 is native-endian premultiplied, i.e. BGRA on little-endian.
+    let format = if cfg!(target_endian = "little") { gdk::MemoryFormat::B8g8r8a8Premultiplied } else { gdk::MemoryFormat::A8r8g8b8Premultiplied };
+    Some(gdk::MemoryTexture::new(size, size, format, &glib::Bytes::from_owned(data), stride).upcast())
+}
+
+// Web pages without an icon get the letter; local and internal ones don't.
+fn gets_letter(uri: &str) -> bool {
+    uri.starts_with("http")
+}
+
+pub fn texture_for(webview: &WebView) -> Option<gdk::Texture> {
+    let uri = webview.uri()?;
+    match webview.favicon() {
+        Some(texture) => Some(texture),
+        None if gets_letter(&uri) => letter_texture(&uri, TAB_ICON_SIZE * 2),
+        None => None,
+    }
+}
+
+pub fn update_image(image: &Image, webview: &WebView) {
+    match texture_for(webview) {
+        Some(texture) => image.set_from_paintable(Some(&texture)),
+        None => image.set_icon_name(Some("web-browser-symbolic")),
+    }
+}
+
+// For tabs without a webview: the letter right away, then the saved icon if
+// the database has one.
+pub fn update_image_for_uri(image: &Image, session: &NetworkSession, uri: &str) {
+    match letter_texture(uri, TAB_ICON_SIZE * 2).filter(|_| gets_letter(uri)) {
+        Some(texture) => image.set_from_paintable(Some(&texture)),
+        None => image.set_icon_name(Some("web-browser-symbolic")),
+    }
+    let database = match session.website_data_manager().favicon_database() { Some(db) => db, None => return };
+    let image = image.downgrade();
+    database.favicon(uri, gio::Cancellable::NONE, move |result| {
+        if let (Some(image), Ok(texture)) = (image.upgrade(), result) { image.set_from_paintable(Some(&texture)); }
+    });
+}
+
+pub fn tab_image() -> Image {
+    let image = Image::from_icon_name("web-browser-symbolic");
+    image.set_pixel_size(TAB_ICON_SIZE);
+    image.set_margin_end(6);
+    image
+}
+
+// PNG for rusty: pages; callers fall back to the letter in HTML when this is None.
+pub fn png_for_page(session: &NetworkSession, page_uri: &str, done: impl FnOnce(Option<Vec<u8>>) + 'static) {
+    let database = match session.website_data_manager().favicon_database() {
+        Some(db) => db,
+        None => return done(None),
+    };
+    database.favicon(page_uri, gio::Cancellable::NONE, move |result| {
+        let png = result.ok()
+            .and_then(|texture| gdk::pixbuf_get_from_texture(&texture))
+            .and_then(|pixbuf| pixbuf.save_to_bufferv("png", &[]).ok());
+        done(png);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{color_for, gets_letter, letter_for};
+
+    #[test]
+    fn letter_is_the_first_of_the_host() {
+        assert_eq!(letter_for("https://example.com/"), "E");
+        assert_eq!(letter_for("https://www.rust-lang.org/learn"), "R");
+        assert_eq!(letter_for("https://-dash.example/"), "D");
+        assert_eq!(letter_for("about:blank"), "?");
+    }
+
+    #[test]
+    fn colour_depends_only_on_the_site() {
+        assert_eq!(color_for("https://example.com/a"), color_for("http://www.example.com/b?c"));
+    }
+
+    #[test]
+    fn only_web_pages_get_a_letter() {
+        assert!(gets_letter("https://example.com/"));
+        assert!(gets_letter("http://example.com/"));
+        assert!(!gets_letter("file:///home/user/page.html"));
+        assert!(!gets_letter("rusty:history"));
+        assert!(!gets_letter(""));
+    }
+}
@@ -0,0 +1,131 @@
+// --- TAB HIBERNATION ---
+use gtk::prelude::*;
+use gtk::{gio, glib, Notebook, Widget};
+use webkit6::prelude::*;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::browser::Browser;
+use crate::tab_label;
+use crate::tab_page::{self, Placeholder};
+use crate::window::BrowserWindow;
+
+// (label, minutes); 0 never hibernates on a timer.
+pub const HIBERNATE_CHOICES: [(&str, u64); 5] = [
+    ("Never", 0),
+    ("After 15 Minutes", 15),
+    ("After 30 Minutes", 30),
+    ("After 1 Hour", 60),
+    ("After 4 Hours", 240),
+];
+
+// Under light memory pressure only tabs left alone this long are put to sleep.
+const LOW_MEMORY_IDLE: Duration = Duration::from_secs(5 * 60);
+
+pub fn choice_names() -> Vec<&'static str> {
+    HIBERNATE_CHOICES.iter().map(|(name, _)| *name).collect()
+}
+
+// Pinned, incognito (their ephemeral session would be lost), blank, selected
+// and audible tabs are left alone.
+fn may_sleep(pinned: bool, incognito: bool, uri: &str, selected: bool, audible: bool) -> bool {
+    !(pinned || incognito || uri.is_empty() || selected || audible)
+}
+
+fn can_hibernate(page: &Widget) -> bool {
+    let header = match tab_label::header(page) { Some(h) => h, None => return false };
+    let current = page.ancestor(Notebook::static_type())
+        .and_then(|nb| nb.downcast::<Notebook>().ok())
+        .and_then(|nb| nb.nth_page(nb.current_page()));
+    let selected = current.as_ref() == Some(page);
+    tab_page::webview(page).map_or(false, |wv| {
+        may_sleep(header.is_pinned(), header.is_incognito(), &header.uri(), selected, wv.is_playing_audio())
+    })
+}
+
+// The configured idle time; None for "Never" or an unknown choice.
+fn idle_limit(choice: usize) -> Option<Duration> {
+    HIBERNATE_CHOICES.get(choice).map(|(_, minutes)| *minutes).filter(|m| *m > 0).map(|m| Duration::from_secs(m * 60))
+}
+
+// Swaps the webview for a placeholder that remembers its history and scroll
+// offset; selecting the tab (or Reload) brings the page back.
+pub fn hibernate(page: &Widget) {
+    let webview = match tab_page::webview(page) { Some(wv) if can_hibernate(page) => wv, _ => return };
+    let page = page.downgrade();
+    let wv = webview.downgrade();
+    tab_page::read_scroll(&webview, move |scroll| {
+        let (page, webview) = match (page.upgrade(), wv.upgrade()) { (Some(p), Some(wv)) => (p, wv), _ => return };
+        // The tab may have been selected while the script ran.
+        if !can_hibernate(&page) || tab_page::webview(&page).as_ref() != Some(&webview) { return; }
+        let header = match tab_label::header(&page) { Some(h) => h, None => return };
+        if let Some(prompts) = BrowserWindow::prompts_for(&page) { prompts.dismiss(&webview); }
+        let placeholder = Placeholder { url: header.uri(), title: header.title(), state: Some(webview.session_state()), scroll };
+        if let Some(page) = tab_page::page(&page) {
+            tab_page::set_placeholder(&page, placeholder, "This tab was put to sleep to save memory.");
+        }
+    });
+}
+
+fn hibernate_idle(notebooks: &[Notebook], idle: Duration) {
+    for notebook in notebooks {
+        for page in (0..notebook.n_pages()).filter_map(|i| notebook.nth_page(Some(i))) {
+            let idle_for = tab_label::header(&page).map_or(Duration::ZERO, |h| h.last_active().elapsed());
+            if idle_for >= idle { hibernate(&page); }
+        }
+    }
+}
+
+// A once-a-minute sweep against the configured idle time, plus whatever the
+// system's memory monitor asks for.
+pub fn watch(browser: &Rc<Browser>) {
+    let monitor = gio::MemoryMonitor::dup_default();
+    let weak = Rc::downgrade(browser);
+    monitor.connect_low_memory_warning(move |_, level| {
+        let browser = match weak.upgrade() { Some(b) => b, None => return };
+        let idle = match level {
+            gio::MemoryMonitorWarningLevel::Low => LOW_MEMORY_IDLE,
+            _ => Duration::ZERO,
+        };
+        hibernate_idle(&browser.notebooks(), idle);
+    });
+
+    let weak = Rc::downgrade(browser);
+    glib::timeout_add_seconds_local(60, move || {
+        // Keeps the monitor (and its handler) alive as long as the browser.
+        let _ = &monitor;
+        let browser = match weak.upgrade() { Some(b) => b, None => return glib::Continue(false) };
+        let limit = idle_limit(browser.state.borrow().hibernate_after as usize);
+        if let Some(limit) = limit { hibernate_idle(&browser.notebooks(), limit); }
+        glib::Continue(true)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{idle_limit, may_sleep};
+    use std::time::Duration;
+
+    #[test]
+    fn background_tabs_with_a_page_may_sleep() {
+        assert!(may_sleep(false, false, "https://example.com/", false, false));
+    }
+
+    #[test]
+    fn some_tabs_never_sleep() {
+        let uri = "https://example.com/";
+        assert!(!may_sleep(true, false, uri, false, false));
+        assert!(!may_sleep(false, true, uri, false, false));
+        assert!(!may_sleep(false, false, "", false, false));
+        assert!(!may_sleep(false, false, uri, true, false));
+        assert!(!may_sleep(false, false, uri, false, true));
+    }
+
+    #[test]
+    fn idle_limits_follow_the_choice() {
+        assert_eq!(idle_limit(0), None);
+        assert_eq!(idle_limit(1), Some(Duration::from_secs(15 * 60)));
+        assert_eq!(idle_limit(4), Some(Duration::from_secs(4 * 60 * 60)));
+        assert_eq!(idle_limit(99), None);
+    }
+}
@@ -0,0 +1,115 @@
+// --- HISTORY ---
+use gtk::glib;
+use serde::{Serialize, Deserialize};
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use crate::{load_json, save_json};
+
+const HISTORY_FILE: &str = "history.json";
+const MAX_ENTRIES: usize = 5000;
+// Visits and title changes are written in batches; titles change often on
+// single-page apps.
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    pub url: String,
+    pub title: String,
+    pub visit_count: u32,
+    pub last_visit: i64,
+}
+
+// Newest first. Amnesia sessions keep it in memory only (save_json is a no-op).
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    #[serde(skip)]
+    shared: Weak<RefCell<History>>,
+    #[serde(skip)]
+    save_queued: bool,
+}
+
+pub type SharedHistory = Rc<RefCell<History>>;
+
+pub fn should_record(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+impl History {
+    pub fn load() -> SharedHistory {
+        Rc::new_cyclic(|weak| RefCell::new(History { shared: weak.clone(), ..load_json(HISTORY_FILE) }))
+    }
+
+    fn save(&mut self) {
+        self.save_queued = false;
+        save_json(HISTORY_FILE, self);
+    }
+
+    fn save_later(&mut self) {
+        if self.save_queued { return; }
+        self.save_queued = true;
+        let shared = self.shared.clone();
+        glib::timeout_add_local_once(SAVE_DELAY, move || {
+            if let Some(history) = shared.upgrade() {
+                let mut history = history.borrow_mut();
+                if history.save_queued { history.save(); }
+            }
+        });
+    }
+
+    // Writes out a batch still waiting, e.g. on exit.
+    pub fn flush(&mut self) {
+        if self.save_queued { self.save(); }
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn record_visit(&mut self, url: &str, title: &str) {
+        let now = glib::real_time() / 1_000_000;
+        match self.entries.iter().position(|e| e.url == url) {
+            Some(idx) => {
+                let mut entry = self.entries.remove(idx);
+                entry.visit_count += 1;
+                entry.last_visit = now;
+                if !title.is_empty() { entry.title = title.to_string(); }
+                self.entries.insert(0, entry);
+            }
+            None => {
+                self.entries.insert(0, HistoryEntry { url: url.to_string(), title: title.to_string(), visit_count: 1, last_visit: now });
+                self.entries.truncate(MAX_ENTRIES);
+            }
+        }
+        self.save_later();
+    }
+
+    // Titles usually arrive after the load finished.
+    pub fn set_title(&mut self, url: &str, title: &str) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.url == url) {
+            if entry.title != title {
+                entry.title = title.to_string();
+                self.save_later();
+            }
+        }
+    }
+
+    pub fn most_visited(&self, count: usize) -> Vec<HistoryEntry> {
+        let mut entries = self.entries.clone();
+        entries.sort_by(|a, b| b.visit_count.cmp(&a.visit_count).then(b.last_visit.cmp(&a.last_visit)));
+        entries.truncate(count);
+        entries
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        self.entries.retain(|e| e.url != url);
+        self.save();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.save();
+    }
+}
@@ -0,0 +1,214 @@
+// --- HTTPS-ONLY MODE ---
+use gtk::prelude::*;
+use gtk::{glib, Align, Box, Button, Label, ListBox, Orientation, PolicyType, ScrolledWindow, SelectionMode, Window};
+use webkit6::prelude::*;
+use webkit6::{LoadEvent, PolicyDecisionType, WebView};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::pages;
+use crate::{navigation_uri, save_config, AppConfig};
+
+// The interstitial's "continue" link points here; decide-policy catches it
+// before WebKit ever tries to resolve the scheme. Any page can link here, so
+// it only counts in the tab showing the interstitial for that very address.
+const BYPASS_PREFIX: &str = "rusty-http-bypass:";
+
+pub type SessionExceptions = Rc<RefCell<HashSet<String>>>;
+
+fn host_of(uri: &str) -> Option<String> {
+    let parsed = glib::Uri::parse(uri, glib::UriFlags::NONE).ok()?;
+    parsed.host().map(|h| h.to_lowercase())
+}
+
+fn is_local_host(host: &str) -> bool {
+    host == "localhost" || host.ends_with(".localhost") || host.ends_with(".local") || host == "::1" || host.starts_with("127.")
+}
+
+// Returns the https:// form of a plain http:// URL that should be upgraded.
+pub fn upgrade_target(uri: &str) -> Option<String> {
+    let rest = uri.strip_prefix("http://")?;
+    let host = host_of(uri)?;
+    if is_local_host(&host) { return None; }
+    Some(format!("https://{}", rest))
+}
+
+fn is_exempt(state: &AppConfig, session: &SessionExceptions, host: &str) -> bool {
+    state.https_only_exceptions.iter().any(|h| h == host) || session.borrow().contains(host)
+}
+
+fn interstitial_html(http_uri: &str) -> String {
+    let host = host_of(http_uri).unwrap_or_default();
+    pages::render("Secure connection unavailable", &format!(r#"
+    <h1>Secure connection unavailable</h1>
+    <p>HTTPS-Only Mode tried to upgrade <code>{host}</code> to a secure connection, but the site doesn't support HTTPS.</p>
+    <p>If you continue, anything you send or receive on this site can be read or changed by others on the network.</p>
+    <a class="button primary" href="javascript:history.back()">Go Back</a>
+    <a class="button secondary" href="{bypass}{uri}">Continue to HTTP Site</a>"#,
+        host = glib::markup_escape_text(&host),
+        bypass = BYPASS_PREFIX,
+        uri = glib::markup_escape_text(http_uri)))
+}
+
+fn interstitial_uri(http_uri: &str) -> String {
+    upgrade_target(http_uri).unwrap_or_else(|| http_uri.to_string())
+}
+
+fn show_interstitial(webview: &WebView, http_uri: &str, shown: &Rc<RefCell<Option<String>>>) {
+    *shown.borrow_mut() = Some(http_uri.to_string());
+    webview.load_alternate_html(&interstitial_html(http_uri), &interstitial_uri(http_uri), None);
+}
+
+// Wires one tab into HTTPS-only mode. `pending` holds the http:// URL of an
+// upgrade in flight, so a failure (or a redirect back to http) lands on the
+// interstitial instead of looping. `shown` is the http:// URL the tab's
+// interstitial is up for, until the tab commits anything else.
+pub fn attach(webview: &WebView, state: Rc<RefCell<AppConfig>>, session: SessionExceptions, is_incognito: bool) {
+    let pending: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    let shown: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+
+    let pending_policy = pending.clone();
+    let shown_policy = shown.clone();
+    webview.connect_decide_policy(move |wv, decision, decision_type| {
+        if decision_type != PolicyDecisionType::NavigationAction { return false; }
+        let uri = match navigation_uri(decision) { Some(u) => u, None => return false };
+
+        if let Some(http_uri) = uri.strip_prefix(BYPASS_PREFIX) {
+            decision.ignore();
+            if shown_policy.borrow_mut().take().as_deref() != Some(http_uri) { return true; }
+            if let Some(host) = host_of(http_uri) {
+                if is_incognito {
+                    session.borrow_mut().insert(host);
+                } else {
+                    let mut config = state.borrow_mut();
+                    if !config.https_only_exceptions.contains(&host) { config.https_only_exceptions.push(host); }
+                    save_config(&config);
+                }
+            }
+            *pending_policy.borrow_mut() = None;
+            wv.load_uri(http_uri);
+            return true;
+        }
+
+        if !state.borrow().https_only { return false; }
+        let target = match upgrade_target(&uri) { Some(t) => t, None => return false };
+        let host = host_of(&uri).unwrap_or_default();
+        if is_exempt(&state.borrow(), &session, &host) { return false; }
+
+        decision.ignore();
+        let already_tried = pending_policy.borrow().as_deref().and_then(host_of).as_deref() == Some(host.as_str());
+        if already_tried {
+            // The https page redirected straight back to http.
+            *pending_policy.borrow_mut() = None;
+            show_interstitial(wv, &uri, &shown_policy);
+        } else {
+            *pending_policy.borrow_mut() = Some(uri);
+            wv.load_uri(&target);
+        }
+        true
+    });
+
+    let pending_load = pending.clone();
+    let shown_load = shown.clone();
+    webview.connect_load_changed(move |wv, event| {
+        if event == LoadEvent::Finished { *pending_load.borrow_mut() = None; }
+        if event == LoadEvent::Committed {
+            let interstitial = shown_load.borrow().as_deref().map(interstitial_uri);
+            if interstitial.is_some() && wv.uri().map(|u| u.to_string()) != interstitial { *shown_load.borrow_mut() = None; }
+        }
+    });
+
+    webview.connect_load_failed(move |wv, _, failing_uri, _| {
+        let http_uri = match pending.borrow_mut().take() { Some(u) => u, None => return false };
+        if host_of(failing_uri) != host_of(&http_uri) { return false; }
+        show_interstitial(wv, &http_uri, &shown);
+        true
+    });
+}
+
+fn refresh_exception_list(list: &ListBox, state: &Rc<RefCell<AppConfig>>) {
+    while let Some(child) = list.first_child() { list.remove(&child); }
+    let hosts = state.borrow().https_only_exceptions.clone();
+    for host in hosts {
+        let row = Box::new(Orientation::Horizontal, 10);
+        row.set_margin_start(6); row.set_margin_end(6);
+        let lbl = Label::new(Some(&host)); lbl.set_hexpand(true); lbl.set_halign(Align::Start);
+        let remove_btn = Button::builder().icon_name("list-remove-symbolic").tooltip_text("Remove Exception").build();
+        row.append(&lbl);
+        row.append(&remove_btn);
+        list.append(&row);
+
+        let state = state.clone();
+        let list_weak = list.downgrade();
+        remove_btn.connect_clicked(move |_| {
+            state.borrow_mut().https_only_exceptions.retain(|h| *h != host);
+            save_config(&state.borrow());
+            if let Some(list) = list_weak.upgrade() { refresh_exception_list(&list, &state); }
+        });
+    }
+}
+
+pub fn show_exceptions_window(parent: &impl IsA<Window>, state: &Rc<RefCell<AppConfig>>) {
+    let window = Window::builder().transient_for(parent).title("HTTPS-Only Exceptions").default_width(400).default_height(400).build();
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_top(20); vbox.set_margin_bottom(20); vbox.set_margin_start(20); vbox.set_margin_end(20);
+    let label = Label::new(None); label.set_markup("<b>Sites Allowed Over HTTP</b>"); label.set_halign(Align::Start); vbox.append(&label);
+
+    let list = ListBox::new();
+    list.set_selection_mode(SelectionMode::None);
+    list.set_placeholder(Some(&Label::new(Some("No exceptions."))));
+    let scroll = ScrolledWindow::builder().hscrollbar_policy(PolicyType::Never).vexpand(true).child(&list).build();
+    vbox.append(&scroll);
+
+    refresh_exception_list(&list, state);
+    window.set_child(Some(&vbox));
+    window.present();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interstitial_uri, is_exempt, is_local_host, upgrade_target, SessionExceptions};
+    use crate::AppConfig;
+
+    #[test]
+    fn plain_http_is_upgraded() {
+        assert_eq!(upgrade_target("http://example.com/a?b=1").as_deref(), Some("https://example.com/a?b=1"));
+        assert_eq!(upgrade_target("http://Example.COM:8080/").as_deref(), Some("https://Example.COM:8080/"));
+    }
+
+    #[test]
+    fn https_and_other_schemes_are_left_alone() {
+        assert_eq!(upgrade_target("https://example.com/"), None);
+        assert_eq!(upgrade_target("file:///tmp/x.html"), None);
+        assert_eq!(upgrade_target("rusty:history"), None);
+    }
+
+    #[test]
+    fn local_hosts_are_never_upgraded() {
+        for host in ["localhost", "app.localhost", "printer.local", "127.0.0.1", "::1"] {
+            assert!(is_local_host(host), "{} should be local", host);
+        }
+        assert!(!is_local_host("example.com"));
+        assert!(!is_local_host("localhost.example.com"));
+        assert_eq!(upgrade_target("http://localhost:8000/"), None);
+        assert_eq!(upgrade_target("http://127.0.0.1/"), None);
+    }
+
+    #[test]
+    fn exceptions_come_from_settings_or_the_session() {
+        let mut state = AppConfig::default();
+        state.https_only_exceptions.push("saved.example".to_string());
+        let session = SessionExceptions::default();
+        session.borrow_mut().insert("once.example".to_string());
+        assert!(is_exempt(&state, &session, "saved.example"));
+        assert!(is_exempt(&state, &session, "once.example"));
+        assert!(!is_exempt(&state, &session, "other.example"));
+    }
+
+    #[test]
+    fn the_interstitial_sits_at_the_upgraded_address() {
+        assert_eq!(interstitial_uri("http://example.com/a"), "https://example.com/a");
+        assert_eq!(interstitial_uri("http://localhost/a"), "http://localhost/a");
+    }
+}
@@ -0,0 +1,391 @@
+// --- RUSTY: SCHEME ---
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use webkit6::prelude::*;
+use webkit6::{NetworkSession, URISchemeRequest, UserContentInjectedFrames, UserContentManager, UserScript, UserScriptInjectionTime, WebContext, WebView};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::bookmarks::SharedBookmarks;
+use crate::crashes::SharedCrashLog;
+use crate::downloads::{self, DownloadState, Downloads};
+use crate::favicons;
+use crate::hibernation;
+use crate::history::SharedHistory;
+use crate::newtab::{self, NEW_TAB_CHOICES};
+use crate::pages::{escape, favicon, render_app};
+use crate::site_data::COOKIE_POLICIES;
+use crate::{is_amnesia_session, save_amnesia_preference, save_config, AppConfig, SEARCH_ENGINES};
+
+pub const SCHEME: &str = "rusty";
+
+// The message handler is only reachable from this script world, so ordinary
+// pages can't see window.webkit.messageHandlers.rusty at all.
+const WORLD: &str = "rusty-internal";
+
+// Runs in WORLD; relays send() from rusty: pages and does nothing elsewhere.
+const BRIDGE_SCRIPT: &str = r#"
+    if (location.protocol === "rusty:") {
+        document.addEventListener("rusty-send", (event) => {
+            if (typeof event.detail === "string") window.webkit.messageHandlers.rusty.postMessage(JSON.parse(event.detail));
+        });
+    }
+"#;
+
+pub fn is_internal(uri: &str) -> bool {
+    uri.starts_with("rusty:")
+}
+
+// A dropdown index sent by a page, if it's one of `count` choices.
+fn choice_index(value: &serde_json::Value, count: usize) -> Option<u32> {
+    value.as_u64().filter(|i| *i < count as u64).map(|i| i as u32)
+}
+
+pub struct InternalPages {
+    state: Rc<RefCell<AppConfig>>,
+    history: SharedHistory,
+    bookmarks: SharedBookmarks,
+    crashes: SharedCrashLog,
+    downloads: Rc<Downloads>,
+    session: Rc<NetworkSession>,
+    on_settings_changed: Rc<dyn Fn()>,
+}
+
+impl InternalPages {
+    pub fn new(state: Rc<RefCell<AppConfig>>, history: SharedHistory, bookmarks: SharedBookmarks, crashes: SharedCrashLog, downloads: Rc<Downloads>, session: Rc<NetworkSession>, on_settings_changed: Rc<dyn Fn()>) -> Rc<Self> {
+        Rc::new(Self { state, history, bookmarks, crashes, downloads, session, on_settings_changed })
+    }
+
+    // Local: web content can't link or redirect to rusty: pages.
+    pub fn register(self: &Rc<Self>, context: &WebContext) {
+        let this = Rc::downgrade(self);
+        context.register_uri_scheme(SCHEME, move |request| {
+            if let Some(this) = this.upgrade() { this.serve(request); }
+        });
+        let security = context.security_manager();
+        security.register_uri_scheme_as_local(SCHEME);
+        security.register_uri_scheme_as_secure(SCHEME);
+    }
+
+    pub fn attach(self: &Rc<Self>, manager: &UserContentManager, webview: &WebView) {
+        manager.register_script_message_handler(SCHEME, Some(WORLD));
+        manager.add_script(&UserScript::for_world(BRIDGE_SCRIPT, UserContentInjectedFrames::TopFrame, UserScriptInjectionTime::Start, WORLD, &[], &[]));
+        let this = Rc::downgrade(self);
+        let webview_weak = webview.downgrade();
+        manager.connect_script_message_received(Some(SCHEME), move |_, value| {
+            let (this, wv) = match (this.upgrade(), webview_weak.upgrade()) { (Some(t), Some(w)) => (t, w), _ => return };
+            if !wv.uri().map_or(false, |u| is_internal(&u)) { return; }
+            let msg: serde_json::Value = match value.to_json(0).and_then(|j| serde_json::from_str(&j).ok()) { Some(m) => m, None => return };
+            this.handle_message(&wv, &msg);
+        });
+    }
+
+    fn serve(&self, request: &URISchemeRequest) {
+        let path = request.path().map(|p| p.to_string()).unwrap_or_default();
+        let (body, mime) = match path.as_str() {
+            "newtab" => (newtab::render(&self.state.borrow(), &self.history.borrow()).into_bytes(), "text/html"),
+            "newtab-background" => match newtab::background_bytes(&self.state.borrow()) {
+                Some((data, mime)) => {
+                    let bytes = glib::Bytes::from_owned(data);
+                    let stream = gio::MemoryInputStream::from_bytes(&bytes);
+                    request.finish(&stream, bytes.len() as i64, Some(&mime));
+                    return;
+                }
+                None => (Vec::new(), "image/png"),
+            },
+            // History and bookmarks are persistent, so icons come from the main session.
+            "favicon" => {
+                let page = request.uri().and_then(|u| u.split_once("?url=").map(|(_, q)| q.to_string()))
+                    .and_then(|q| glib::Uri::unescape_string(&q, None)).map(|u| u.to_string()).unwrap_or_default();
+                let request = request.clone();
+                favicons::png_for_page(&self.session, &page, move |png| match png {
+                    Some(data) => {
+                        let bytes = glib::Bytes::from_owned(data);
+                        let stream = gio::MemoryInputStream::from_bytes(&bytes);
+                        request.finish(&stream, bytes.len() as i64, Some("image/png"));
+                    }
+                    None => request.finish_error(&mut glib::Error::new(gio::IOErrorEnum::NotFound, "No favicon")),
+                });
+                return;
+            }
+            "settings" => (self.settings_page().into_bytes(), "text/html"),
+            "history" => (self.history_page().into_bytes(), "text/html"),
+            "bookmarks" => (self.bookmarks_page().into_bytes(), "text/html"),
+            "downloads" => (self.downloads_page().into_bytes(), "text/html"),
+            "crashes" => (self.crashes_page().into_bytes(), "text/html"),
+            "about" => (about_page().into_bytes(), "text/html"),
+            "version" => (version_page().into_bytes(), "text/html"),
+            "logo.png" => (include_bytes!("logo.png").to_vec(), "image/png"),
+            _ => (not_found_page(&path).into_bytes(), "text/html"),
+        };
+        let bytes = glib::Bytes::from_owned(body);
+        let stream = gio::MemoryInputStream::from_bytes(&bytes);
+        request.finish(&stream, bytes.len() as i64, Some(mime));
+    }
+
+    fn handle_message(&self, webview: &WebView, msg: &serde_json::Value) {
+        let action = msg["action"].as_str().unwrap_or_default();
+        let url = msg["url"].as_str().unwrap_or_default();
+        let id = msg["id"].as_u64().unwrap_or(u64::MAX);
+        match action {
+            "set" => {
+                self.apply_setting(msg["key"].as_str().unwrap_or_default(), &msg["value"]);
+                return;
+            }
+            "newtab-pin" => newtab::pin(&mut self.state.borrow_mut(), url, msg["title"].as_str().unwrap_or_default()),
+            "newtab-unpin" => newtab::unpin(&mut self.state.borrow_mut(), url),
+            "newtab-clear-background" => {
+                let mut state = self.state.borrow_mut();
+                state.new_tab_background.clear();
+                save_config(&state);
+            }
+            "newtab-pick-background" => {
+                newtab::pick_background(webview, self.state.clone());
+                return;
+            }
+            "history-remove" => self.history.borrow_mut().remove(url),
+            "history-clear" => self.history.borrow_mut().clear(),
+            "bookmark-remove" => self.bookmarks.borrow_mut().remove(url),
+            "crashes-clear" => self.crashes.borrow_mut().clear(),
+            "download-open" => {
+                if let Some(path) = self.downloads.get(id).and_then(|e| e.destination()) { downloads::open_path(&path); }
+                return;
+            }
+            "download-show" => {
+                if let Some(path) = self.downloads.get(id).and_then(|e| e.destination()) {
+                    if let Some(dir) = std::path::Path::new(&path).parent() { downloads::open_path(&dir.to_string_lossy()); }
+                }
+                return;
+            }
+            "download-cancel" => {
+                if let Some(entry) = self.downloads.get(id) { entry.download.cancel(); }
+            }
+            "downloads-clear" => self.downloads.clear_inactive(),
+            "refresh" => {}
+            _ => return,
+        }
+        webview.reload();
+    }
+
+    fn apply_setting(&self, key: &str, value: &serde_json::Value) {
+        {
+            let mut state = self.state.borrow_mut();
+            match (key, value) {
+                ("homepage", serde_json::Value::String(v)) => state.homepage = v.clone(),
+                ("search_engine_index", v) => {
+                    let idx = v.as_u64().unwrap_or(0) as u32;
+                    if let Some((_, url)) = SEARCH_ENGINES.get(idx as usize) {
+                        state.search_engine_index = idx;
+                        state.search_engine_url = url.to_string();
+                    }
+                }
+                ("new_tab_page", v) => match choice_index(v, NEW_TAB_CHOICES.len()) { Some(i) => state.new_tab_page = i, None => return },
+                ("hibernate_after", v) => match choice_index(v, hibernation::HIBERNATE_CHOICES.len()) { Some(i) => state.hibernate_after = i, None => return },
+                ("cookie_policy", v) => match choice_index(v, COOKIE_POLICIES.len()) { Some(i) => state.cookie_policy = i, None => return },
+                ("show_home_button", serde_json::Value::Bool(v)) => state.show_home_button = *v,
+                ("restore_session", serde_json::Value::Bool(v)) => state.restore_session = *v,
+                ("vertical_tabs", serde_json::Value::Bool(v)) => state.vertical_tabs = *v,
+                ("tabs_next_to_current", serde_json::Value::Bool(v)) => state.tabs_next_to_current = *v,
+                ("use_hw_accel", serde_json::Value::Bool(v)) => state.use_hw_accel = *v,
+                ("enable_adblock", serde_json::Value::Bool(v)) => state.enable_adblock = *v,
+                ("https_only", serde_json::Value::Bool(v)) => state.https_only = *v,
+                ("notifications_dnd", serde_json::Value::Bool(v)) => state.notifications_dnd = *v,
+                ("clear_on_exit", serde_json::Value::Bool(v)) => state.clear_on_exit = *v,
+                ("amnesia_mode", serde_json::Value::Bool(v)) => {
+                    state.amnesia_mode = *v;
+                    if is_amnesia_session() { save_amnesia_preference(*v); }
+                }
+                _ => return,
+            }
+            save_config(&state);
+        }
+        (self.on_settings_changed)();
+    }
+
+    fn settings_page(&self) -> String {
+        let state = self.state.borrow();
+        let toggle = |key: &str, label: &str, on: bool| {
+            format!(r#"<label class="setting"><span>{}</span><input type="checkbox" {} onchange="send({{action: 'set', key: '{}', value: this.checked}})"></label>"#,
+                label, if on { "checked" } else { "" }, key)
+        };
+        let select = |key: &str, options: Vec<&str>, selected: u32| {
+            let opts: String = options.iter().enumerate()
+                .map(|(i, name)| format!(r#"<option value="{}" {}>{}</option>"#, i, if i as u32 == selected { "selected" } else { "" }, escape(name)))
+                .collect();
+            format!(r#"<select onchange="send({{action: 'set', key: '{}', value: Number(this.value)}})">{}</select>"#, key, opts)
+        };
+        let amnesia_note = if is_amnesia_session() { "<p class=\"empty\">This session is in Amnesia Mode. Settings are kept in memory only.</p>" } else { "" };
+
+        render_app("Settings", &format!(r#"
+    <h1>Settings</h1>
+    {amnesia_note}
+    <h2>General</h2>
+    <label class="setting"><span>Homepage</span><input type="text" size="40" value="{homepage}" onchange="send({{action: 'set', key: 'homepage', value: this.value}})"></label>
+    {home_button}
+    <div class="setting"><span>New tabs open</span>{new_tab}</div>
+    {restore}
+    {vertical}
+    {next_to_current}
+    <div class="setting"><span>Hibernate background tabs</span>{hibernate}</div>
+    <h2>Search Engine</h2>
+    <div class="setting"><span>Default search engine</span>{engines}</div>
+    <h2>Performance &amp; Privacy</h2>
+    {hw}
+    {adblock}
+    {https}
+    {dnd}
+    {clear_exit}
+    {amnesia}
+    <h2>Cookies</h2>
+    <div class="setting"><span>Cookie policy</span>{cookies}</div>
+    <p class="meta">Site data, permissions and exceptions are managed from the Settings dialog. See also <a href="rusty:history">History</a>, <a href="rusty:bookmarks">Bookmarks</a>, <a href="rusty:downloads">Downloads</a> and <a href="rusty:about">About</a>.</p>"#,
+            amnesia_note = amnesia_note,
+            homepage = escape(&state.homepage),
+            home_button = toggle("show_home_button", "Show Home Button", state.show_home_button),
+            vertical = toggle("vertical_tabs", "Vertical Tabs", state.vertical_tabs),
+            next_to_current = toggle("tabs_next_to_current", "Open New Tabs Next to the Current Tab", state.tabs_next_to_current),
+            hibernate = select("hibernate_after", hibernation::choice_names(), state.hibernate_after),
+            restore = toggle("restore_session", "Restore Tabs on Startup", state.restore_session),
+            new_tab = select("new_tab_page", NEW_TAB_CHOICES.to_vec(), state.new_tab_page),
+            engines = select("search_engine_index", SEARCH_ENGINES.iter().map(|(name, _)| *name).collect(), state.search_engine_index),
+            hw = toggle("use_hw_accel", "Hardware Acceleration", state.use_hw_accel),
+            adblock = toggle("enable_adblock", "AdBlock", state.enable_adblock),
+            https = toggle("https_only", "HTTPS-Only Mode", state.https_only),
+            dnd = toggle("notifications_dnd", "Do Not Disturb (silence web notifications)", state.notifications_dnd),
+            clear_exit = toggle("clear_on_exit", "Clear Browsing Data on Exit", state.clear_on_exit),
+            amnesia = toggle("amnesia_mode", "Amnesia Mode (applies on restart)", state.amnesia_mode),
+            cookies = select("cookie_policy", COOKIE_POLICIES.to_vec(), state.cookie_policy)))
+    }
+
+    fn history_page(&self) -> String {
+        let history = self.history.borrow();
+        let rows: String = history.entries().iter().map(|e| {
+            let when = glib::DateTime::from_unix_local(e.last_visit).ok().and_then(|d| d.format("%Y-%m-%d %H:%M").ok()).map(|s| s.to_string()).unwrap_or_default();
+            let title = if e.title.is_empty() { &e.url } else { &e.title };
+            format!(r#"<div class="row" data-filter="{title} {url}">{icon}<div class="grow"><div class="title"><a href="{url}">{title}</a></div><div class="url">{url}</div></div><span class="url">{when}</span><button data-url="{url}" onclick="send({{action: 'history-remove', url: this.dataset.url}})">Remove</button></div>"#,
+                icon = favicon(&e.url), title = escape(title), url = escape(&e.url), when = when)
+        }).collect();
+        let rows = if rows.is_empty() { "<p class=\"empty\">No history yet.</p>".to_string() } else { rows };
+        render_app("History", &format!(r#"
+    <h1>History</h1>
+    <input type="search" placeholder="Search history" oninput="filterRows(this.value)">
+    {rows}
+    <p><button onclick="if (confirm('Clear all history?')) send({{action: 'history-clear'}})">Clear History</button></p>"#, rows = rows))
+    }
+
+    fn crashes_page(&self) -> String {
+        let crashes = self.crashes.borrow();
+        let rows: String = crashes.entries().iter().map(|e| {
+            let when = glib::DateTime::from_unix_local(e.time).ok().and_then(|d| d.format("%Y-%m-%d %H:%M").ok()).map(|s| s.to_string()).unwrap_or_default();
+            let page = if e.url.is_empty() {
+                "<div class=\"title\">Incognito tab</div>".to_string()
+            } else {
+                let title = if e.title.is_empty() { &e.url } else { &e.title };
+                format!(r#"<div class="title"><a href="{url}">{title}</a></div><div class="url">{url}</div>"#, title = escape(title), url = escape(&e.url))
+            };
+            format!(r#"<div class="row"><div class="grow">{page}</div><span>{reason}</span><span class="url">{when}</span></div>"#,
+                page = page, reason = escape(&e.reason), when = when)
+        }).collect();
+        let rows = if rows.is_empty() { "<p class=\"empty\">No pages have crashed.</p>".to_string() } else { rows };
+        render_app("Crashes", &format!(r#"
+    <h1>Crashes</h1>
+    {rows}
+    <p><button onclick="send({{action: 'crashes-clear'}})">Clear Log</button></p>"#, rows = rows))
+    }
+
+    fn bookmarks_page(&self) -> String {
+        let bookmarks = self.bookmarks.borrow();
+        let rows: String = bookmarks.items().iter().map(|b| {
+            let title = if b.title.is_empty() { &b.url } else { &b.title };
+            format!(r#"<div class="row" data-filter="{title} {url}">{icon}<div class="grow"><div class="title"><a href="{url}">{title}</a></div><div class="url">{url}</div></div><button data-url="{url}" onclick="send({{action: 'bookmark-remove', url: this.dataset.url}})">Remove</button></div>"#,
+                icon = favicon(&b.url), title = escape(title), url = escape(&b.url))
+        }).collect();
+        let rows = if rows.is_empty() { "<p class=\"empty\">No bookmarks yet. Use the star in the address bar to add one.</p>".to_string() } else { rows };
+        render_app("Bookmarks", &format!(r#"
+    <h1>Bookmarks</h1>
+    <input type="search" placeholder="Search bookmarks" oninput="filterRows(this.value)">
+    {rows}"#, rows = rows))
+    }
+
+    fn downloads_page(&self) -> String {
+        let items = self.downloads.items();
+        let rows: String = items.iter().map(|e| {
+            let path = e.destination().unwrap_or_default();
+            let name = std::path::Path::new(&path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| e.source.clone());
+            let (status, actions) = match e.state.get() {
+                DownloadState::Running => (
+                    format!("{:.0}% of {}", e.download.estimated_progress() * 100.0, glib::format_size(e.download.received_data_length())),
+                    format!(r#"<button onclick="send({{action: 'download-cancel', id: {}}})">Cancel</button>"#, e.id),
+                ),
+                DownloadState::Finished => (
+                    "Done".to_string(),
+                    format!(r#"<button onclick="send({{action: 'download-open', id: {id}}})">Open</button><button onclick="send({{action: 'download-show', id: {id}}})">Show in Folder</button>"#, id = e.id),
+                ),
+                DownloadState::Failed => ("Failed".to_string(), String::new()),
+                DownloadState::Cancelled => ("Cancelled".to_string(), String::new()),
+            };
+            format!(r#"<div class="row"><div class="grow"><div class="title">{name}</div><div class="url">{source}</div></div><span class="url">{status}</span>{actions}</div>"#,
+                name = escape(&name), source = escape(&e.source), status = status, actions = actions)
+        }).collect();
+        let running = items.iter().any(|e| e.state.get() == DownloadState::Running);
+        let rows = if rows.is_empty() { "<p class=\"empty\">No downloads this session.</p>".to_string() } else { rows };
+        // Progress isn't pushed to the page; poll while anything is running.
+        let poll = if running { "<script>setTimeout(() => send({action: 'refresh'}), 1000);</script>" } else { "" };
+        render_app("Downloads", &format!(r#"
+    <h1>Downloads</h1>
+    {rows}
+    <p><button onclick="send({{action: 'downloads-clear'}})">Clear Finished</button></p>
+    {poll}"#, rows = rows, poll = poll))
+    }
+}
+
+fn about_page() -> String {
+    render_app("About Rusty Browser", &format!(r#"
+    <h1><img src="rusty:logo.png" width="64" height="64" alt="">Rusty Browser</h1>
+    <p>A privacy-focused, high-performance browser. Built with Rust, GTK4 &amp; WebKit.</p>
+    <p>Version {version}. See <a href="rusty:version">rusty:version</a> for details.</p>
+    <p><a href="https://wyind.dev">Website</a> · <a href="https://github.com/wyind/rusty_browser">Source Code</a> · <a href="https://ko-fi.com/wyind">Buy Me A Coffee</a></p>
+    <p class="meta">© 2025 wyind.dev. Distributed under the Apache License 2.0.</p>"#,
+        version = env!("CARGO_PKG_VERSION")))
+}
+
+fn version_page() -> String {
+    render_app("Version", &format!(r#"
+    <h1>Version</h1>
+    <div class="row"><span class="grow">Rusty Browser</span><code>{app}</code></div>
+    <div class="row"><span class="grow">WebKitGTK</span><code>{wk_major}.{wk_minor}.{wk_micro}</code></div>
+    <div class="row"><span class="grow">GTK</span><code>{gtk_major}.{gtk_minor}.{gtk_micro}</code></div>
+    <div class="row"><span class="grow">Amnesia Session</span><code>{amnesia}</code></div>"#,
+        app = env!("CARGO_PKG_VERSION"),
+        wk_major = webkit6::major_version(), wk_minor = webkit6::minor_version(), wk_micro = webkit6::micro_version(),
+        gtk_major = gtk::major_version(), gtk_minor = gtk::minor_version(), gtk_micro = gtk::micro_version(),
+        amnesia = if is_amnesia_session() { "yes" } else { "no" }))
+}
+
+fn not_found_page(path: &str) -> String {
+    render_app("Not Found", &format!(r#"
+    <h1>Unknown page</h1>
+    <p><code>rusty:{}</code> doesn't exist. Try <a href="rusty:settings">settings</a>, <a href="rusty:history">history</a>, <a href="rusty:bookmarks">bookmarks</a>, <a href="rusty:downloads">downloads</a>, <a href="rusty:crashes">crashes</a>, <a href="rusty:about">about</a> or <a href="rusty:version">version</a>.</p>"#,
+        escape(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{choice_index, is_internal};
+
+    #[test]
+    fn choice_index_stays_in_range() {
+        assert_eq!(choice_index(&serde_json::json!(0), 3), Some(0));
+        assert_eq!(choice_index(&serde_json::json!(2), 3), Some(2));
+        assert_eq!(choice_index(&serde_json::json!(3), 3), None);
+        assert_eq!(choice_index(&serde_json::json!(u64::MAX), 3), None);
+        assert_eq!(choice_index(&serde_json::json!(-1), 3), None);
+        assert_eq!(choice_index(&serde_json::json!("1"), 3), None);
+    }
+
+    #[test]
+    fn internal_pages_use_the_rusty_scheme() {
+        assert!(is_internal("rusty:settings"));
+        assert!(!is_internal("https://rusty.example/"));
+    }
+}
@@ -1,26 +1,60 @@
 // --- IMPORTS ---
+mod bookmarks;
+mod browser;
+mod clear_data;
+mod crashes;
+mod default_browser;
+mod downloads;
+mod error_pages;
+mod favicons;
+mod hibernation;
+mod history;
+mod https_only;
+mod internal;
+mod newtab;
+mod notifications;
+mod page_menu;
+mod pages;
+mod permissions;
+mod session;
+mod sidebar;
+mod site_data;
+mod tab_groups;
+mod tab_label;
+mod tab_menu;
+mod tab_page;
+mod tls;
+mod window;
+
 use gtk::prelude::*;
-use gtk::glib; 
-use gtk::{
-    Application, ApplicationWindow, Box, Orientation, Entry, Button, 
-    Label, Dialog, ResponseType, Switch, Separator, LinkButton,
-    ScrolledWindow, PolicyType, CssProvider, ProgressBar, DropDown, StringList,
-    Image, Window, Align
-};
-use gtk::gdk_pixbuf::PixbufLoader;
+use gtk::{gio, glib};
+use gtk::{Application, CssProvider};
 use gtk::gdk;
 use webkit6::prelude::*;
-use webkit6::{WebView, HardwareAccelerationPolicy, WebContext, UserContentManager, UserStyleSheet, UserContentInjectedFrames, UserStyleLevel};
+use webkit6::{PolicyDecision, NavigationPolicyDecision};
 use std::env;
+use std::ffi::OsStr;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::fs;
-use std::path::PathBuf;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
 
 // --- CONFIGURATION STRUCTS ---
 
+const SEARCH_ENGINES: [(&str, &str); 4] = [
+    ("DuckDuckGo", "https://duckduckgo.com/?q="),
+    ("Google", "https://www.google.com/search?q="),
+    ("Bing", "https://www.bing.com/search?q="),
+    ("Brave", "https://search.brave.com/search?q="),
+];
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 struct AppConfig {
     homepage: String,
     use_hw_accel: bool,
@@ -29,6 +63,27 @@ struct AppConfig {
     show_home_button: bool,
     search_engine_url: String,
     search_engine_index: u32,
+    clear_on_exit: bool,
+    cookie_policy: u32,
+    cookie_exceptions: Vec<CookieException>,
+    notifications_dnd: bool,
+    muted_notification_sites: Vec<String>,
+    https_only: bool,
+    https_only_exceptions: Vec<String>,
+    new_tab_page: u32,
+    speed_dial: Vec<newtab::SpeedDialEntry>,
+    new_tab_background: String,
+    restore_session: bool,
+    vertical_tabs: bool,
+    vertical_tabs_collapsed: bool,
+    tabs_next_to_current: bool,
+    hibernate_after: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct CookieException {
+    domain: String,
+    allow: bool,
 }
 
 impl Default for AppConfig {
@@ -41,36 +96,131 @@ impl Default for AppConfig {
             show_home_button: true,
             search_engine_url: "https://duckduckgo.com/?q=".to_string(),
             search_engine_index: 0,
+            clear_on_exit: false,
+            cookie_policy: 1,
+            cookie_exceptions: Vec::new(),
+            notifications_dnd: false,
+            muted_notification_sites: Vec::new(),
+            https_only: false,
+            https_only_exceptions: Vec::new(),
+            new_tab_page: 0,
+            speed_dial: Vec::new(),
+            new_tab_background: String::new(),
+            restore_session: false,
+            vertical_tabs: false,
+            vertical_tabs_collapsed: false,
+            tabs_next_to_current: true,
+            hibernate_after: 2,
         }
     }
 }
 
+// --- AMNESIA ---
+// Decided once at startup (--amnesia or the saved setting). While set, nothing
+// is written to disk: no settings, no website data, no third-party caches.
+static AMNESIA_SESSION: AtomicBool = AtomicBool::new(false);
+
+const SIGTERM: i32 = 15;
+
+fn is_amnesia_session() -> bool {
+    AMNESIA_SESSION.load(Ordering::Relaxed)
+}
+
+// Must run before GTK/WebKit start so child processes inherit the environment.
+// Returns the scratch directory to delete on shutdown, or None if there is
+// nowhere private to put caches, in which case the session must not start.
+fn enter_amnesia_mode() -> Option<PathBuf> {
+    // GStreamer's registry and fontconfig still insist on a cache directory,
+    // so hand them a private one on the runtime tmpfs (or in the temp
+    // directory without one) instead of ~/.cache.
+    let base = env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).unwrap_or_else(env::temp_dir);
+    let scratch = base.join(format!("rusty_browser-amnesia-{}", std::process::id()));
+    // Created fresh and private; an existing directory might not be ours.
+    fs::DirBuilder::new().mode(0o700).create(&scratch).ok()?;
+
+    AMNESIA_SESSION.store(true, Ordering::Relaxed);
+    env::set_var("MESA_SHADER_CACHE_DISABLE", "true");
+    env::set_var("MESA_GLSL_CACHE_DISABLE", "true");
+    env::set_var("XDG_CACHE_HOME", &scratch);
+    Some(scratch)
+}
+
+// --- PROFILES ---
+// Set once from --profile. Each profile keeps its own settings, session and
+// website data under profiles/<name>, and runs as its own instance.
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+const APP_ID: &str = "com.titan.rustybrowser";
+
+// Safe both as a directory name and as an application id segment.
+fn profile_slug(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+fn profile_path(mut base: PathBuf) -> PathBuf {
+    base.push("rusty_browser");
+    if let Some(name) = PROFILE.get() {
+        base.push("profiles");
+        base.push(name);
+    }
+    base
+}
+
+// WebKit's own default locations belong to the default profile.
+fn profile_storage_dirs() -> Option<(String, String)> {
+    PROFILE.get()?;
+    let data = profile_path(glib::user_data_dir());
+    let cache = profile_path(glib::user_cache_dir());
+    Some((data.to_string_lossy().into_owned(), cache.to_string_lossy().into_owned()))
+}
+
 // --- SAVE/LOAD HELPERS ---
-fn get_config_path() -> PathBuf {
-    let mut path = glib::user_config_dir();
-    path.push("rusty_browser");
-    std::fs::create_dir_all(&path).unwrap_or_default();
-    path.push("settings.json");
+fn get_config_file(name: &str) -> PathBuf {
+    let mut path = profile_path(glib::user_config_dir());
+    path.push(name);
     path
 }
 
-fn load_config() -> AppConfig {
-    let path = get_config_path();
-    if let Ok(contents) = fs::read_to_string(path) {
-        if let Ok(config) = serde_json::from_str(&contents) {
-            return config;
+fn load_json<T: DeserializeOwned + Default>(name: &str) -> T {
+    if let Ok(contents) = fs::read_to_string(get_config_file(name)) {
+        if let Ok(value) = serde_json::from_str(&contents) {
+            return value;
         }
     }
-    AppConfig::default()
+    T::default()
 }
 
-fn save_config(config: &AppConfig) {
-    let path = get_config_path();
-    if let Ok(json) = serde_json::to_string_pretty(config) {
+fn save_json<T: Serialize>(name: &str, value: &T) {
+    if is_amnesia_session() { return; }
+    write_json(name, value);
+}
+
+fn write_json<T: Serialize>(name: &str, value: &T) {
+    let path = get_config_file(name);
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(value) {
         let _ = fs::write(path, json);
     }
 }
 
+fn load_config() -> AppConfig {
+    load_json("settings.json")
+}
+
+fn save_config(config: &AppConfig) {
+    save_json("settings.json", config);
+}
+
+// The one write allowed during an amnesia session: the user explicitly
+// flipping the startup mode. Only that field changes on disk.
+fn save_amnesia_preference(enabled: bool) {
+    let mut on_disk = load_config();
+    on_disk.amnesia_mode = enabled;
+    write_json("settings.json", &on_disk);
+}
+
 fn main() {
     // --- CONFIGURATION ---
     env::set_var("GDK_BACKEND", "x11");
@@ -78,9 +228,46 @@ fn main() {
     env::set_var("LIBVA_DRIVER_NAME", "nvidia");
     env::set_var("WEBKIT_DISABLE_SANDBOX_GPU_PROCESS", "1");
 
+    // One instance per profile: a second launch hands its command line to the
+    // running browser and exits.
     let app = Application::builder()
-        .application_id("com.titan.rustybrowser")
+        .application_id(APP_ID)
+        .flags(gio::ApplicationFlags::HANDLES_OPEN | gio::ApplicationFlags::HANDLES_COMMAND_LINE)
         .build();
+    let no_short = glib::Char::from(b'\0');
+    app.add_main_option("new-window", no_short, glib::OptionFlags::NONE, glib::OptionArg::None, "Open the given pages in a new window", None);
+    app.add_main_option("incognito", no_short, glib::OptionFlags::NONE, glib::OptionArg::None, "Open the given pages in incognito tabs", None);
+    app.add_main_option("profile", no_short, glib::OptionFlags::NONE, glib::OptionArg::String, "Use a separate profile with its own settings and data", Some("NAME"));
+    app.add_main_option("amnesia", no_short, glib::OptionFlags::NONE, glib::OptionArg::None, "Start a session that writes nothing to disk", None);
+    app.add_main_option("desktop-entry", no_short, glib::OptionFlags::NONE, glib::OptionArg::None, "Print a desktop entry for this binary and exit", None);
+
+    // Runs in every launching process, before it registers or forwards anything.
+    let amnesia_scratch: Rc<RefCell<Option<PathBuf>>> = Rc::new(RefCell::new(None));
+    let scratch_local = amnesia_scratch.clone();
+    app.connect_handle_local_options(move |app, options| {
+        if options.contains("desktop-entry") {
+            print!("{}", default_browser::desktop_entry());
+            return 0;
+        }
+        let profile = options.lookup::<String>("profile").ok().flatten().map(|p| profile_slug(&p)).filter(|p| !p.is_empty());
+        if let Some(profile) = profile {
+            app.set_application_id(Some(&format!("{}.profile_{}", APP_ID, profile)));
+            let _ = PROFILE.set(profile);
+        }
+        // An --amnesia launch never joins (or becomes) the normal instance.
+        let amnesia_flag = options.contains("amnesia");
+        if amnesia_flag { app.set_flags(app.flags() | gio::ApplicationFlags::NON_UNIQUE); }
+        if amnesia_flag || load_config().amnesia_mode {
+            match enter_amnesia_mode() {
+                Some(scratch) => *scratch_local.borrow_mut() = Some(scratch),
+                None => {
+                    eprintln!("rusty_browser: no private directory for Amnesia Mode's caches; not starting");
+                    return 1;
+                }
+            }
+        }
+        -1
+    });
 
     app.connect_startup(|_| {
         let provider = CssProvider::new();
@@ -111,6 +298,10 @@ fn main() {
             .about-version { color: #808080; margin-bottom: 20px; }
             .about-box { padding: 30px; }
 
+            /* Sleeping and crashed tabs */
+            .tab-placeholder { color: #b0b0b0; }
+            .tab-placeholder .title-2 { font-size: 20px; font-weight: bold; color: #ececec; }
+
             /* Progress Bar */
             progressbar trough { min-height: 2px; background: transparent; border: none; }
             progressbar progress { background-color: #3daee9; min-height: 2px; border-radius: 0; }
@@ -134,6 +325,39 @@ fn main() {
                 background-color: rgba(255, 80, 80, 0.2);
                 color: #ff5f56;
             }
+            .tab-audio-btn {
+                min-width: 16px;
+                min-height: 16px;
+                padding: 0;
+                margin-left: 4px;
+                background-color: transparent;
+                color: #808080;
+                border-radius: 100%;
+            }
+            .tab-audio-btn:hover { color: white; }
+            .tab-crashed { color: #ff5f56; }
+            .tab-selected { background-color: rgba(61, 174, 233, 0.15); border-radius: 4px; }
+
+            /* Vertical Tabs */
+            .tab-sidebar { background-color: #151516; border-right: 1px solid #000000; }
+            .tab-sidebar list { background-color: transparent; }
+            .tab-sidebar row { padding: 4px 8px; color: #b0b0b0; }
+            .tab-sidebar row:selected { background-color: #1e1e20; color: white; box-shadow: inset 2px 0 #3daee9; }
+
+            /* Tab Groups */
+            .tab-group-chip { min-height: 18px; padding: 0 8px; margin: 0 6px 0 0; border-radius: 9px; color: #1e1e20; font-size: 11px; font-weight: bold; }
+            .tab-group-chip.group-blue, box.group-blue { border-bottom: 2px solid #3daee9; }
+            .tab-group-chip.group-green, box.group-green { border-bottom: 2px solid #5abf73; }
+            .tab-group-chip.group-orange, box.group-orange { border-bottom: 2px solid #e89a3d; }
+            .tab-group-chip.group-purple, box.group-purple { border-bottom: 2px solid #bf66d9; }
+            .tab-group-chip.group-red, box.group-red { border-bottom: 2px solid #e85c66; }
+            .tab-group-chip.group-grey, box.group-grey { border-bottom: 2px solid #808080; }
+            .tab-group-chip.group-blue { background-color: #3daee9; }
+            .tab-group-chip.group-green { background-color: #5abf73; }
+            .tab-group-chip.group-orange { background-color: #e89a3d; }
+            .tab-group-chip.group-purple { background-color: #bf66d9; }
+            .tab-group-chip.group-red { background-color: #e85c66; }
+            .tab-group-chip.group-grey { background-color: #808080; }
         ");
 
         if let Some(display) = gdk::Display::default() {
@@ -141,357 +365,115 @@ fn main() {
         }
     });
 
-    app.connect_activate(build_ui);
-    app.run();
-}
-
-fn build_ui(app: &Application) {
-    // LOAD SETTINGS FROM DISK
-    let loaded_config = load_config();
-    let app_state = Rc::new(RefCell::new(loaded_config));
-
-    // --- PERSISTENCE ---
-    let persistent_context = WebContext::default().unwrap();
-    let shared_persistent_context = Rc::new(persistent_context);
-
-    let window = ApplicationWindow::builder()
-        .application(app)
-        .title("Rusty Browser")
-        .default_width(1200)
-        .default_height(800)
-        .build();
-
-    let main_box = Box::new(Orientation::Vertical, 0);
-    window.set_child(Some(&main_box));
-
-    // --- TOOLBAR ---
-    let toolbar = Box::new(Orientation::Horizontal, 0);
-    toolbar.add_css_class("toolbar"); 
-    
-    let back_btn = Button::builder().icon_name("go-previous-symbolic").tooltip_text("Go Back").build();
-    let forward_btn = Button::builder().icon_name("go-next-symbolic").tooltip_text("Go Forward").build();
-    let refresh_btn = Button::builder().icon_name("view-refresh-symbolic").tooltip_text("Reload").build();
-    let home_btn = Button::builder().icon_name("go-home-symbolic").tooltip_text("Home").build();
-    
-    // Apply loaded visibility state
-    home_btn.set_visible(app_state.borrow().show_home_button);
-
-    let url_bar = Entry::new();
-    url_bar.set_hexpand(true);
-    url_bar.set_placeholder_text(Some("Search or enter URL"));
-    
-    let new_tab_btn = Button::builder().icon_name("tab-new-symbolic").tooltip_text("New Tab").build();
-    let incognito_btn = Button::builder().icon_name("weather-clear-night-symbolic").tooltip_text("Incognito").build();
-    incognito_btn.add_css_class("incognito-btn");
-    let settings_btn = Button::builder().icon_name("emblem-system-symbolic").tooltip_text("Settings").build();
-
-    toolbar.append(&back_btn);
-    toolbar.append(&forward_btn);
-    toolbar.append(&refresh_btn);
-    toolbar.append(&home_btn);
-    toolbar.append(&url_bar);
-    toolbar.append(&new_tab_btn);
-    toolbar.append(&incognito_btn);
-    toolbar.append(&settings_btn);
-    main_box.append(&toolbar);
-
-    let progress_bar = ProgressBar::new();
-    progress_bar.set_visible(false);
-    main_box.append(&progress_bar);
-
-    let notebook = gtk::Notebook::new();
-    notebook.set_scrollable(true);
-    notebook.set_vexpand(true);
-    main_box.append(&notebook);
-
-    // --- TAB LOGIC ---
-    let notebook_weak = notebook.downgrade();
-    let url_bar_weak = url_bar.downgrade();
-    let window_weak = window.downgrade();
-    let progress_bar_weak = progress_bar.downgrade();
-    let state_clone = app_state.clone();
-    let persistent_ctx_clone = shared_persistent_context.clone();
-
-    let create_tab = Rc::new(move |url: &str, is_incognito: bool| {
-        let notebook = match notebook_weak.upgrade() {
-            Some(n) => n,
-            None => return,
-        };
-
-        let user_manager = UserContentManager::new();
-
-        if state_clone.borrow().enable_adblock {
-            let adblock_css = "iframe[src*='ads'], div[class*='ad-'], div[id*='google_ads'], .adsbygoogle, .ad-banner { display: none !important; }";
-            let style = UserStyleSheet::new(adblock_css, UserContentInjectedFrames::AllFrames, UserStyleLevel::User, &[], &[]);
-            user_manager.add_style_sheet(&style);
-        }
-
-        let webview: WebView;
-        if is_incognito || state_clone.borrow().amnesia_mode {
-            let ephemeral_ctx = WebContext::new(); 
-            webview = glib::Object::builder().property("web-context", &ephemeral_ctx).property("user-content-manager", &user_manager).build();
-        } else {
-            webview = glib::Object::builder().property("web-context", persistent_ctx_clone.as_ref()).property("user-content-manager", &user_manager).build();
-        }
-        
-        let use_accel = state_clone.borrow().use_hw_accel;
-
-        if let Some(settings) = WebViewExt::settings(&webview) {
-            if use_accel {
-                settings.set_hardware_acceleration_policy(HardwareAccelerationPolicy::Always);
-                settings.set_enable_webgl(true);
-            } else {
-                settings.set_hardware_acceleration_policy(HardwareAccelerationPolicy::Never);
-                settings.set_enable_webgl(false);
+    // Every way in ends up here: our own command line or one forwarded from a
+    // second launch, desktop "open" requests, and plain activation.
+    let browser: Rc<RefCell<Option<Rc<browser::Browser>>>> = Rc::new(RefCell::new(None));
+    let launch: Rc<dyn Fn(&Application, Vec<String>, bool, bool)> = Rc::new(move |app, urls, new_window, incognito| {
+        let existing = browser.borrow().clone();
+        match existing {
+            // A bare relaunch gets a fresh window rather than a tab in an old one.
+            Some(browser) => browser.open_urls(&urls, new_window || urls.is_empty(), incognito),
+            None => {
+                let created = browser::Browser::new(app);
+                let restored = created.restore_session();
+                if !restored || new_window || incognito || !urls.is_empty() { created.open_urls(&urls, new_window, incognito); }
+                *browser.borrow_mut() = Some(created);
             }
-            settings.set_enable_media_stream(true);
-            settings.set_enable_mediasource(true);
-            settings.set_enable_smooth_scrolling(false); 
-            settings.set_enable_developer_extras(true);
-            settings.set_user_agent(Some("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36"));
         }
+    });
 
-        webview.load_uri(url);
-
-        let tab_box = Box::new(Orientation::Horizontal, 0);
-        tab_box.set_valign(Align::Center);
-        
-        let label_text = if is_incognito { "🕵️ Loading..." } else { "Loading..." };
-        let tab_label = Label::new(Some(label_text));
-        
-        let close_btn = Button::builder().icon_name("window-close-symbolic").build();
-        close_btn.add_css_class("tab-close-btn");
-
-        tab_box.append(&tab_label);
-        tab_box.append(&close_btn);
-        tab_box.show();
-
-        let page_idx = notebook.append_page(&webview, Some(&tab_box));
-        notebook.set_tab_reorderable(&webview, true);
-        notebook.set_current_page(Some(page_idx));
-
-        let nb_close = notebook.clone();
-        let wv_close = webview.clone();
-        close_btn.connect_clicked(move |_| {
-            if let Some(idx) = nb_close.page_num(&wv_close) {
-                nb_close.remove_page(Some(idx));
-            }
-        });
-
-        let url_bar_weak = url_bar_weak.clone();
-        webview.connect_uri_notify(move |wv| {
-            if let Some(u) = url_bar_weak.upgrade() { if let Some(uri) = wv.uri() { u.set_text(uri.as_str()); } }
-        });
-
-        let window_weak_title = window_weak.clone();
-        let notebook_weak_title = notebook.downgrade();
-        let label_clone = tab_label.clone();
-
-        webview.connect_title_notify(move |wv| {
-             if let Some(title) = wv.title() {
-                let short_title = if is_incognito { 
-                    format!("🕵️ {}", &title.as_str()[0..std::cmp::min(15, title.as_str().len())]) 
-                } else { 
-                    format!("{}", &title.as_str()[0..std::cmp::min(15, title.as_str().len())]) 
-                };
-                label_clone.set_text(&short_title);
-
-                if let (Some(win), Some(nb)) = (window_weak_title.upgrade(), notebook_weak_title.upgrade()) {
-                    if let Some(page_idx) = nb.page_num(wv) {
-                        if Some(page_idx) == nb.current_page() {
-                            win.set_title(Some(&format!("{} - Rusty Browser", title.as_str())));
-                        }
-                    }
-                }
-             }
-        });
-
-        let progress_weak = progress_bar_weak.clone();
-        webview.connect_estimated_load_progress_notify(move |wv| {
-            if let Some(bar) = progress_weak.upgrade() {
-                let progress = wv.estimated_load_progress();
-                bar.set_fraction(progress);
-                if progress >= 1.0 { bar.set_visible(false); } else { bar.set_visible(true); }
-            }
-        });
+    let launch_activate = launch.clone();
+    app.connect_activate(move |app| launch_activate(app, Vec::new(), false, false));
 
-        notebook.show();
+    let launch_open = launch.clone();
+    app.connect_open(move |app, files, _| {
+        launch_open(app, files.iter().map(|f| f.uri().to_string()).collect(), false, false);
     });
 
-    (create_tab)(&app_state.borrow().homepage, false);
-
-    // --- ACTIONS ---
-    let create_tab_clone = create_tab.clone();
-    let state_clone_new = app_state.clone();
-    new_tab_btn.connect_clicked(move |_| { (create_tab_clone)(&state_clone_new.borrow().homepage, false); });
-
-    let create_tab_incog = create_tab.clone();
-    let state_clone_incog = app_state.clone();
-    incognito_btn.connect_clicked(move |_| { (create_tab_incog)(&state_clone_incog.borrow().homepage, true); });
-
-    let notebook_clone = notebook.clone();
-    let state_clone_search = app_state.clone();
-    url_bar.connect_activate(move |entry| {
-        if let Some(page) = notebook_clone.nth_page(notebook_clone.current_page()) {
-            if let Ok(webview) = page.downcast::<WebView>() {
-                let input = entry.text().to_string();
-                let target_url = if input.contains("://") { input } 
-                else if !input.contains('.') || input.contains(' ') {
-                     let engine = &state_clone_search.borrow().search_engine_url;
-                     format!("{}{}", engine, input)
-                } else { format!("https://{}", input) };
-                webview.load_uri(&target_url);
-            }
-        }
+    app.connect_command_line(move |app, cmdline| {
+        let options = cmdline.options_dict();
+        let cwd = cmdline.cwd();
+        let urls = cmdline.arguments().iter().skip(1).map(|arg| command_line_target(cwd.as_deref(), arg)).collect();
+        launch(app, urls, options.contains("new-window"), options.contains("incognito"));
+        0
     });
 
-    let notebook_clone = notebook.clone();
-    back_btn.connect_clicked(move |_| {
-        if let Some(page) = notebook_clone.nth_page(notebook_clone.current_page()) {
-            if let Ok(webview) = page.downcast::<WebView>() { if webview.can_go_back() { webview.go_back(); } }
-        }
+    // A SIGTERM (logout, kill) ends the run like quitting does, so the
+    // amnesia scratch directory below is still removed.
+    let app_term = app.downgrade();
+    glib::unix_signal_add_local(SIGTERM, move || {
+        if let Some(app) = app_term.upgrade() { app.quit(); }
+        glib::Continue(false)
     });
 
-    let notebook_clone = notebook.clone();
-    forward_btn.connect_clicked(move |_| {
-        if let Some(page) = notebook_clone.nth_page(notebook_clone.current_page()) {
-            if let Ok(webview) = page.downcast::<WebView>() { if webview.can_go_forward() { webview.go_forward(); } }
-        }
-    });
+    app.run();
 
-    let notebook_clone = notebook.clone();
-    refresh_btn.connect_clicked(move |_| {
-        if let Some(page) = notebook_clone.nth_page(notebook_clone.current_page()) {
-            if let Ok(webview) = page.downcast::<WebView>() { webview.reload(); }
-        }
-    });
+    if let Some(dir) = amnesia_scratch.borrow().as_ref() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
 
-    let notebook_clone = notebook.clone();
-    let state_clone_home = app_state.clone();
-    home_btn.connect_clicked(move |_| {
-        if let Some(page) = notebook_clone.nth_page(notebook_clone.current_page()) {
-            if let Ok(webview) = page.downcast::<WebView>() { webview.load_uri(&state_clone_home.borrow().homepage); }
-        }
-    });
+// Files are resolved against the launching process's directory; anything
+// else is passed on as typed, like url bar input.
+fn command_line_target(cwd: Option<&Path>, arg: &OsStr) -> String {
+    let text = arg.to_string_lossy();
+    if !text.contains("://") {
+        let file = match cwd {
+            Some(cwd) => gio::File::for_commandline_arg_and_cwd(arg, cwd),
+            None => gio::File::for_commandline_arg(arg),
+        };
+        if file.query_exists(gio::Cancellable::NONE) { return file.uri().to_string(); }
+    }
+    text.into_owned()
+}
 
-    let notebook_clone = notebook.clone();
-    let url_bar_clone = url_bar.clone();
-    let window_clone = window.clone();
-    notebook.connect_switch_page(move |_, widget, _| {
-        if let Ok(webview) = widget.clone().downcast::<WebView>() {
-            if let Some(uri) = webview.uri() { url_bar_clone.set_text(&uri); }
-            if let Some(title) = webview.title() { window_clone.set_title(Some(&format!("{} - Rusty Browser", title))); }
-            else { window_clone.set_title(Some("Rusty Browser")); }
-        }
-    });
+fn navigation_uri(decision: &PolicyDecision) -> Option<String> {
+    let nav = decision.downcast_ref::<NavigationPolicyDecision>()?;
+    let mut action = nav.navigation_action()?;
+    let request = action.request()?;
+    request.uri().map(|u| u.to_string())
+}
 
-    // --- SETTINGS ---
-    let window_clone = window.clone();
-    let state_clone_settings = app_state.clone();
-    let home_btn_clone = home_btn.clone();
-
-    settings_btn.connect_clicked(move |_| {
-        let dialog = Dialog::builder().transient_for(&window_clone).modal(true).title("Settings").build();
-        dialog.add_button("Close", ResponseType::Close);
-        let content_area = dialog.content_area(); 
-        let scroll = ScrolledWindow::builder().hscrollbar_policy(PolicyType::Never).min_content_width(400).min_content_height(400).build();
-        let vbox = Box::new(Orientation::Vertical, 10);
-        vbox.set_margin_top(20); vbox.set_margin_bottom(20); vbox.set_margin_start(20); vbox.set_margin_end(20);
-
-        let label_gen = Label::new(None); label_gen.set_markup("<b>General</b>"); label_gen.set_halign(gtk::Align::Start); vbox.append(&label_gen);
-        let home_entry = Entry::new(); home_entry.set_text(&state_clone_settings.borrow().homepage); vbox.append(&home_entry);
-        let show_home_switch = Switch::new(); show_home_switch.set_active(state_clone_settings.borrow().show_home_button);
-        let show_home_box = Box::new(Orientation::Horizontal, 10); show_home_box.append(&show_home_switch); show_home_box.append(&Label::new(Some("Show Home Button"))); vbox.append(&show_home_box);
-
-        vbox.append(&Separator::new(Orientation::Horizontal));
-        let label_search = Label::new(None); label_search.set_markup("<b>Search Engine</b>"); label_search.set_halign(gtk::Align::Start); vbox.append(&label_search);
-        let engines = StringList::new(&["DuckDuckGo", "Google", "Bing", "Brave"]);
-        let dropdown = DropDown::new(Some(engines), Option::<gtk::Expression>::None);
-        dropdown.set_selected(state_clone_settings.borrow().search_engine_index);
-        vbox.append(&dropdown);
-
-        vbox.append(&Separator::new(Orientation::Horizontal));
-        let label_perf = Label::new(None); label_perf.set_markup("<b>Performance &amp; Privacy</b>"); label_perf.set_halign(gtk::Align::Start); vbox.append(&label_perf);
-        let hw_switch = Switch::new(); hw_switch.set_active(state_clone_settings.borrow().use_hw_accel);
-        let hw_box = Box::new(Orientation::Horizontal, 10); hw_box.append(&hw_switch); hw_box.append(&Label::new(Some("Hardware Acceleration"))); vbox.append(&hw_box);
-        let ad_switch = Switch::new(); ad_switch.set_active(state_clone_settings.borrow().enable_adblock);
-        let ad_box = Box::new(Orientation::Horizontal, 10); ad_box.append(&ad_switch); ad_box.append(&Label::new(Some("AdBlock"))); vbox.append(&ad_box);
-        let amnesia_switch = Switch::new(); amnesia_switch.set_active(state_clone_settings.borrow().amnesia_mode);
-        let amnesia_box = Box::new(Orientation::Horizontal, 10); amnesia_box.append(&amnesia_switch); amnesia_box.append(&Label::new(Some("Amnesia Mode"))); vbox.append(&amnesia_box);
-        let warn_lbl = Label::new(None); warn_lbl.set_markup("<i>(Changes require opening a new tab)</i>"); warn_lbl.set_halign(gtk::Align::Start); warn_lbl.set_sensitive(false); vbox.append(&warn_lbl);
-
-        vbox.append(&Separator::new(Orientation::Horizontal));
-        let label_about = Label::new(None); label_about.set_markup("<b>About</b>"); label_about.set_halign(gtk::Align::Start); vbox.append(&label_about);
-        let about_btn = Button::builder().label("About Rusty Browser").build();
-        about_btn.add_css_class("flat-button");
-        vbox.append(&about_btn);
-        vbox.append(&LinkButton::with_label("https://github.com/wyind/rusty_browser", "Source Code"));
-        vbox.append(&LinkButton::with_label("https://ko-fi.com/wyind", "Donate"));
-
-        scroll.set_child(Some(&vbox));
-        content_area.append(&scroll);
-
-        let window_weak_for_about = window_clone.downgrade();
-        about_btn.connect_clicked(move |_| {
-            if let Some(parent_window) = window_weak_for_about.upgrade() {
-                let logo_bytes = include_bytes!("logo.png");
-                let loader = PixbufLoader::new();
-                loader.write(logo_bytes).unwrap();
-                loader.close().unwrap();
-                let pixbuf = loader.pixbuf().unwrap();
-                let texture = gdk::Texture::for_pixbuf(&pixbuf);
-
-                let about_window = Window::builder().transient_for(&parent_window).modal(true).title("About").default_width(350).default_height(400).resizable(false).decorated(true).build();
-                let vbox = Box::new(Orientation::Vertical, 15);
-                vbox.add_css_class("about-box");
-                vbox.set_margin_top(20); vbox.set_margin_bottom(20); vbox.set_margin_start(20); vbox.set_margin_end(20);
-
-                let img = Image::from_paintable(Some(&texture));
-                img.set_pixel_size(128);
-                vbox.append(&img);
-                let title = Label::new(None); title.set_markup("<span size='xx-large' weight='bold'>Rusty Browser</span>"); vbox.append(&title);
-                let version = Label::new(Some("Version 1.0.0 (Titan Edition)")); version.add_css_class("about-version"); vbox.append(&version);
-                let desc = Label::new(Some("A privacy-focused, high-performance browser.\nBuilt with Rust, GTK4 & WebKit."));
-                desc.set_wrap(true); desc.set_justify(gtk::Justification::Center); vbox.append(&desc);
-                vbox.append(&Separator::new(Orientation::Horizontal));
-                vbox.append(&LinkButton::with_label("https://wyind.dev", "Website"));
-                vbox.append(&LinkButton::with_label("https://github.com/wyind/rusty_browser", "Source Code"));
-                vbox.append(&LinkButton::with_label("https://ko-fi.com/wyind", "Buy Me A Coffee"));
-                vbox.append(&Label::new(Some("© 2025 wyind.dev")));
-
-                about_window.set_child(Some(&vbox));
-                about_window.present();
-            }
-        });
 
-        let state_clone = state_clone_settings.clone();
-        let home_btn_action = home_btn_clone.clone();
+#[cfg(test)]
+mod tests {
+    use super::{command_line_target, profile_slug};
+    use std::ffi::OsStr;
+    use std::fs;
 
-        dialog.connect_response(move |d, _| {
-            state_clone.borrow_mut().homepage = home_entry.text().to_string();
-            state_clone.borrow_mut().use_hw_accel = hw_switch.is_active();
-            state_clone.borrow_mut().enable_adblock = ad_switch.is_active();
-            state_clone.borrow_mut().amnesia_mode = amnesia_switch.is_active();
-            
-            let show_home = show_home_switch.is_active();
-            state_clone.borrow_mut().show_home_button = show_home;
-            home_btn_action.set_visible(show_home);
-
-            let idx = dropdown.selected();
-            state_clone.borrow_mut().search_engine_index = idx;
-            match idx {
-                0 => state_clone.borrow_mut().search_engine_url = "https://duckduckgo.com/?q=".to_string(),
-                1 => state_clone.borrow_mut().search_engine_url = "https://www.google.com/search?q=".to_string(),
-                2 => state_clone.borrow_mut().search_engine_url = "https://www.bing.com/search?q=".to_string(),
-                3 => state_clone.borrow_mut().search_engine_url = "https://search.brave.com/search?q=".to_string(),
-                _ => {}
-            }
-            // SAVE CONFIG
-            save_config(&state_clone.borrow());
-            d.close();
-        });
-        dialog.show();
-    });
+    #[test]
+    fn profile_slug_replaces_unsafe_characters() {
+        assert_eq!(profile_slug("work"), "work");
+        assert_eq!(profile_slug("my-profile_2"), "my-profile_2");
+        assert_eq!(profile_slug("Work Stuff"), "Work_Stuff");
+        assert_eq!(profile_slug("../café"), "___caf_");
+    }
 
-    window.present();
+    #[test]
+    fn profile_slug_of_nothing_is_empty() {
+        // The caller treats an empty slug as "no profile".
+        assert_eq!(profile_slug(""), "");
+    }
+
+    #[test]
+    fn command_line_target_resolves_existing_files() {
+        let dir = std::env::temp_dir().join(format!("rusty_browser-target-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("page.html"), "<p>hi</p>").unwrap();
+        let relative = command_line_target(Some(&dir), OsStr::new("page.html"));
+        let absolute = command_line_target(None, dir.join("page.html").as_os_str());
+        let _ = fs::remove_dir_all(&dir);
+
+        let expected = format!("file://{}/page.html", dir.display());
+        assert_eq!(relative, expected);
+        assert_eq!(absolute, expected);
+    }
+
+    #[test]
+    fn command_line_target_passes_everything_else_through() {
+        let dir = std::env::temp_dir();
+        assert_eq!(command_line_target(Some(&dir), OsStr::new("https://example.com/a")), "https://example.com/a");
+        assert_eq!(command_line_target(Some(&dir), OsStr::new("example.com")), "example.com");
+        assert_eq!(command_line_target(Some(&dir), OsStr::new("rusty:history")), "rusty:history");
+    }
 }
@@ -0,0 +1,197 @@
+// --- NEW TAB PAGE ---
+use gtk::prelude::*;
+use gtk::{gio, glib, FileChooserAction, FileChooserNative, FileFilter, ResponseType, Window};
+use webkit6::WebView;
+use serde::{Serialize, Deserialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::history::History;
+use crate::pages::{escape, favicon, render_app};
+use crate::{save_config, AppConfig};
+
+pub const NEW_TAB_URI: &str = "rusty:newtab";
+pub const NEW_TAB_CHOICES: [&str; 3] = ["New Tab Page", "Homepage", "Blank Page"];
+const TOP_SITES: usize = 8;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpeedDialEntry {
+    pub url: String,
+    pub title: String,
+}
+
+pub fn new_tab_url(state: &AppConfig) -> String {
+    match state.new_tab_page {
+        0 => NEW_TAB_URI.to_string(),
+        1 => state.homepage.clone(),
+        _ => "about:blank".to_string(),
+    }
+}
+
+// The new tab page leaves the omnibar empty, ready for typing.
+pub fn display_uri(uri: &str) -> &str {
+    if uri == NEW_TAB_URI { "" } else { uri }
+}
+
+fn tile_label(url: &str, title: &str) -> String {
+    if !title.is_empty() { return title.to_string(); }
+    glib::Uri::parse(url, glib::UriFlags::NONE).ok().and_then(|u| u.host()).map(|h| h.trim_start_matches("www.").to_string()).unwrap_or_else(|| url.to_string())
+}
+
+fn tile(url: &str, title: &str, action: &str, action_label: &str) -> String {
+    format!(r#"<div class="tile"><a href="{url}">{icon}<span class="name">{name}</span></a><button title="{action_label}" data-url="{url}" data-title="{title}" onclick="send({{action: '{action}', url: this.dataset.url, title: this.dataset.title}})">{symbol}</button></div>"#,
+        url = escape(url),
+        icon = favicon(url),
+        name = escape(&tile_label(url, title)),
+        title = escape(title),
+        action = action,
+        action_label = action_label,
+        symbol = if action == "newtab-unpin" { "×" } else { "+" })
+}
+
+const NEW_TAB_STYLE: &str = r#"
+    body { background-size: cover; background-position: center; }
+    main { max-width: 760px; }
+    form.search { margin: 10vh 0 32px; }
+    form.search input { width: 100%; box-sizing: border-box; font-size: 18px; padding: 12px 18px; border-radius: 24px; }
+    .tiles { display: grid; grid-template-columns: repeat(auto-fill, minmax(120px, 1fr)); gap: 14px; }
+    .tile { position: relative; background: rgba(42, 42, 44, 0.85); border-radius: 10px; }
+    .tile a { display: flex; flex-direction: column; align-items: center; gap: 8px; padding: 16px 8px; text-decoration: none; color: #ececec; }
+    .tile .fav { width: 40px; height: 40px; font-size: 18px; }
+    .tile .name { font-size: 12px; max-width: 100%; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+    .tile button { position: absolute; top: 4px; right: 4px; padding: 0 6px; visibility: hidden; }
+    .tile:hover button { visibility: visible; }
+    .add { display: flex; gap: 8px; margin-top: 16px; }
+    .add input { flex: 1; }
+    .footer { margin-top: 32px; display: flex; gap: 8px; }
+"#;
+
+pub fn render(state: &AppConfig, history: &History) -> String {
+    let pinned: String = state.speed_dial.iter().map(|e| tile(&e.url, &e.title, "newtab-unpin", "Unpin")).collect();
+    let top: String = history.most_visited(TOP_SITES + state.speed_dial.len())
+        .into_iter()
+        .filter(|e| !state.speed_dial.iter().any(|p| p.url == e.url))
+        .take(TOP_SITES)
+        .map(|e| tile(&e.url, &e.title, "newtab-pin", "Pin to Speed Dial"))
+        .collect();
+    let top_section = if top.is_empty() { String::new() } else { format!(r#"<h2>Most Visited</h2><div class="tiles">{}</div>"#, top) };
+    let background = if state.new_tab_background.is_empty() {
+        String::new()
+    } else {
+        r#"<style>body { background-image: linear-gradient(rgba(30, 30, 32, 0.55), rgba(30, 30, 32, 0.55)), url("rusty:newtab-background"); }</style>"#.to_string()
+    };
+    let clear_background = if state.new_tab_background.is_empty() { "" } else { r#"<button onclick="send({action: 'newtab-clear-background'})">Remove Background</button>"# };
+
+    render_app("New Tab", &format!(r#"
+    <style>{style}</style>
+    {background}
+    <form class="search" data-engine="{engine}" onsubmit="event.preventDefault(); const q = this.q.value.trim(); if (q) location.href = this.dataset.engine + encodeURIComponent(q);">
+        <input type="search" name="q" placeholder="Search the web" autofocus>
+    </form>
+    <h2>Speed Dial</h2>
+    <div class="tiles">{pinned}</div>
+    <form class="add" onsubmit="event.preventDefault(); if (this.url.value) send({{action: 'newtab-pin', url: this.url.value, title: this.title.value}});">
+        <input type="text" name="url" placeholder="https://example.com">
+        <input type="text" name="title" placeholder="Name (optional)">
+        <button type="submit">Pin</button>
+    </form>
+    {top_section}
+    <div class="footer">
+        <button onclick="send({{action: 'newtab-pick-background'}})">Choose Background…</button>
+        {clear_background}
+    </div>"#,
+        style = NEW_TAB_STYLE,
+        background = background,
+        engine = escape(&state.search_engine_url),
+        pinned = pinned,
+        top_section = top_section,
+        clear_background = clear_background))
+}
+
+// Only ever a local file; the new tab page never touches the network.
+pub fn background_bytes(state: &AppConfig) -> Option<(Vec<u8>, String)> {
+    if state.new_tab_background.is_empty() { return None; }
+    let data = std::fs::read(&state.new_tab_background).ok()?;
+    let (mime, _) = gio::content_type_guess(Some(&state.new_tab_background), &data);
+    let mime = gio::content_type_get_mime_type(&mime).map(|m| m.to_string()).unwrap_or_else(|| "application/octet-stream".to_string());
+    Some((data, mime))
+}
+
+pub fn pin(state: &mut AppConfig, url: &str, title: &str) {
+    let url = if url.contains("://") { url.to_string() } else { format!("https://{}", url) };
+    if !state.speed_dial.iter().any(|e| e.url == url) {
+        state.speed_dial.push(SpeedDialEntry { url, title: title.to_string() });
+        save_config(state);
+    }
+}
+
+pub fn unpin(state: &mut AppConfig, url: &str) {
+    state.speed_dial.retain(|e| e.url != url);
+    save_config(state);
+}
+
+pub fn pick_background(webview: &WebView, state: Rc<RefCell<AppConfig>>) {
+    let parent = webview.root().and_then(|r| r.downcast::<Window>().ok());
+    let chooser = FileChooserNative::new(Some("Choose Background"), parent.as_ref(), FileChooserAction::Open, Some("Choose"), Some("Cancel"));
+    let filter = FileFilter::new();
+    filter.set_name(Some("Images"));
+    filter.add_mime_type("image/*");
+    chooser.add_filter(&filter);
+
+    let webview_weak = webview.downgrade();
+    chooser.connect_response(move |chooser, response| {
+        if response == ResponseType::Accept {
+            if let Some(path) = chooser.file().and_then(|f| f.path()) {
+                let mut state = state.borrow_mut();
+                state.new_tab_background = path.to_string_lossy().to_string();
+                save_config(&state);
+            }
+            if let Some(wv) = webview_weak.upgrade() { wv.reload(); }
+        }
+        chooser.destroy();
+    });
+    chooser.show();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{display_uri, new_tab_url, render, tile_label, SpeedDialEntry, NEW_TAB_URI};
+    use crate::history::History;
+    use crate::AppConfig;
+
+    #[test]
+    fn new_tab_url_follows_the_setting() {
+        let mut state = AppConfig::default();
+        state.homepage = "https://home.example/".to_string();
+        state.new_tab_page = 0;
+        assert_eq!(new_tab_url(&state), NEW_TAB_URI);
+        state.new_tab_page = 1;
+        assert_eq!(new_tab_url(&state), "https://home.example/");
+        state.new_tab_page = 2;
+        assert_eq!(new_tab_url(&state), "about:blank");
+    }
+
+    #[test]
+    fn only_the_new_tab_page_hides_its_address() {
+        assert_eq!(display_uri(NEW_TAB_URI), "");
+        assert_eq!(display_uri("https://example.com/"), "https://example.com/");
+        assert_eq!(display_uri("about:blank"), "about:blank");
+    }
+
+    #[test]
+    fn tiles_fall_back_to_the_host() {
+        assert_eq!(tile_label("https://www.example.com/a", ""), "example.com");
+        assert_eq!(tile_label("https://example.com/a", "Example"), "Example");
+        assert_eq!(tile_label("not a url", ""), "not a url");
+    }
+
+    #[test]
+    fn speed_dial_tiles_are_escaped() {
+        let mut state = AppConfig::default();
+        state.speed_dial.push(SpeedDialEntry { url: "https://example.com/?a=1&b=2".to_string(), title: "<b>Example</b>".to_string() });
+        let html = render(&state, &History::default());
+        assert!(html.contains("&lt;b&gt;Example&lt;/b&gt;"));
+        assert!(!html.contains("<b>Example</b>"));
+        assert!(html.contains("newtab-unpin"));
+    }
+}
@@ -0,0 +1,141 @@
+// --- WEB NOTIFICATIONS ---
+use gtk::prelude::*;
+use gtk::{gio, glib, Application, Notebook, Window};
+use webkit6::prelude::*;
+use webkit6::WebView;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::permissions::{origin_of, PermissionKind, SharedPermissions, SitePermissions};
+use crate::tab_page;
+use crate::{save_config, AppConfig};
+
+// Only sites the user allowed, and never while muted or in Do Not Disturb.
+fn should_forward(state: &AppConfig, permissions: &SitePermissions, origin: &str) -> bool {
+    permissions.get(origin, PermissionKind::Notifications) == Some(true)
+        && !state.notifications_dnd
+        && !state.muted_notification_sites.iter().any(|o| o == origin)
+}
+
+// Forwards WebKit notifications to the desktop through GApplication, so they
+// land in the system notification center and survive the window losing focus.
+pub struct NotificationBridge {
+    app: glib::WeakRef<Application>,
+    state: Rc<RefCell<AppConfig>>,
+    permissions: SharedPermissions,
+    pending: RefCell<HashMap<String, (glib::WeakRef<WebView>, webkit6::Notification)>>,
+    next_id: Cell<u64>,
+}
+
+impl NotificationBridge {
+    pub fn new(app: &Application, state: Rc<RefCell<AppConfig>>, permissions: SharedPermissions) -> Rc<Self> {
+        let bridge = Rc::new(Self { app: app.downgrade(), state, permissions, pending: RefCell::new(HashMap::new()), next_id: Cell::new(0) });
+
+        let clicked = gio::SimpleAction::new("notification-clicked", Some(glib::VariantTy::STRING));
+        let this = Rc::downgrade(&bridge);
+        clicked.connect_activate(move |_, param| {
+            if let (Some(this), Some(id)) = (this.upgrade(), param.and_then(|p| p.get::<String>())) { this.activate(&id); }
+        });
+        app.add_action(&clicked);
+
+        let mute = gio::SimpleAction::new("mute-notifications", Some(glib::VariantTy::STRING));
+        let this = Rc::downgrade(&bridge);
+        mute.connect_activate(move |_, param| {
+            if let (Some(this), Some(origin)) = (this.upgrade(), param.and_then(|p| p.get::<String>())) { this.set_muted(&origin, true); }
+        });
+        app.add_action(&mute);
+
+        bridge
+    }
+
+    pub fn is_muted(&self, origin: &str) -> bool {
+        self.state.borrow().muted_notification_sites.iter().any(|o| o == origin)
+    }
+
+    pub fn set_muted(&self, origin: &str, muted: bool) {
+        let mut state = self.state.borrow_mut();
+        state.muted_notification_sites.retain(|o| o != origin);
+        if muted { state.muted_notification_sites.push(origin.to_string()); }
+        save_config(&state);
+    }
+
+    // Always returns true: anything we don't forward is dropped, never left to WebKit.
+    pub fn show(self: &Rc<Self>, webview: &WebView, notification: &webkit6::Notification) -> bool {
+        let app = match self.app.upgrade() { Some(a) => a, None => return true };
+        let origin = match webview.uri().and_then(|u| origin_of(&u)) { Some(o) => o, None => return true };
+        if !should_forward(&self.state.borrow(), &self.permissions.borrow(), &origin) { return true; }
+
+        let id = format!("web-notification-{}", self.next_id.get());
+        self.next_id.set(self.next_id.get() + 1);
+
+        let title = notification.title().map(|t| t.to_string()).unwrap_or_else(|| origin.clone());
+        let native = gio::Notification::new(&title);
+        let body = notification.body().map(|b| b.to_string()).unwrap_or_default();
+        native.set_body(Some(&format!("{}\n{}", body, origin).trim().to_string()));
+        native.set_default_action_and_target_value("app.notification-clicked", Some(&id.to_variant()));
+        native.add_button_with_target_value("Mute This Site", "app.mute-notifications", Some(&origin.to_variant()));
+        app.send_notification(Some(&id), &native);
+
+        // The page can close its notification; mirror that on the desktop.
+        let this = Rc::downgrade(self);
+        let closed_id = id.clone();
+        notification.connect_closed(move |_| {
+            if let Some(this) = this.upgrade() {
+                this.pending.borrow_mut().remove(&closed_id);
+                if let Some(app) = this.app.upgrade() { app.withdraw_notification(&closed_id); }
+            }
+        });
+
+        self.pending.borrow_mut().insert(id, (webview.downgrade(), notification.clone()));
+        true
+    }
+
+    fn activate(&self, id: &str) {
+        let (webview, notification) = match self.pending.borrow_mut().remove(id) { Some(p) => p, None => return };
+        notification.clicked();
+        let webview = match webview.upgrade() { Some(w) => w, None => return };
+        if let Some(notebook) = webview.ancestor(Notebook::static_type()).and_then(|w| w.downcast::<Notebook>().ok()) {
+            if let Some(idx) = tab_page::page(&webview).and_then(|p| notebook.page_num(&p)) { notebook.set_current_page(Some(idx)); }
+        }
+        if let Some(window) = webview.root().and_then(|r| r.downcast::<Window>().ok()) { window.present(); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::should_forward;
+    use crate::permissions::{PermissionKind, SitePermissions};
+    use crate::AppConfig;
+
+    const SITE: &str = "https://example.com";
+
+    fn allowed() -> SitePermissions {
+        let mut permissions = SitePermissions::default();
+        permissions.set(SITE, PermissionKind::Notifications, Some(true));
+        permissions
+    }
+
+    #[test]
+    fn needs_a_stored_allow() {
+        let state = AppConfig::default();
+        assert!(should_forward(&state, &allowed(), SITE));
+        assert!(!should_forward(&state, &SitePermissions::default(), SITE));
+        let mut denied = SitePermissions::default();
+        denied.set(SITE, PermissionKind::Notifications, Some(false));
+        assert!(!should_forward(&state, &denied, SITE));
+        assert!(!should_forward(&state, &allowed(), "https://other.example"));
+    }
+
+    #[test]
+    fn do_not_disturb_silences_everything() {
+        let state = AppConfig { notifications_dnd: true, ..AppConfig::default() };
+        assert!(!should_forward(&state, &allowed(), SITE));
+    }
+
+    #[test]
+    fn muted_sites_stay_quiet() {
+        let state = AppConfig { muted_notification_sites: vec![SITE.to_string()], ..AppConfig::default() };
+        assert!(!should_forward(&state, &allowed(), SITE));
+    }
+}
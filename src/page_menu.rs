@@ -0,0 +1,216 @@
+// --- PAGE CONTEXT MENU ---
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use webkit6::prelude::*;
+use webkit6::{ContextMenu, ContextMenuAction, ContextMenuItem, HitTestResult, WebView};
+use std::rc::Weak;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::browser::Browser;
+use crate::window::BrowserWindow;
+use crate::SEARCH_ENGINES;
+
+// Query parameters that only exist to track where a click came from.
+const TRACKING_PARAMS: [&str; 14] = [
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "mc_cid", "mc_eid",
+    "igshid", "_hsenc", "_hsmi", "mkt_tok", "oly_enc_id",
+];
+
+fn is_tracking_param(name: &str) -> bool {
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name)
+}
+
+// The link without its tracking parameters; None when there was nothing to strip.
+pub fn clean_link(uri: &str) -> Option<String> {
+    let (rest, fragment) = match uri.split_once('#') { Some((r, f)) => (r, Some(f)), None => (uri, None) };
+    let (base, query) = rest.split_once('?')?;
+    let params: Vec<&str> = query.split('&').filter(|p| !p.is_empty()).collect();
+    let kept: Vec<&str> = params.iter().copied().filter(|p| !is_tracking_param(p.split('=').next().unwrap_or(p))).collect();
+    if kept.len() == params.len() { return None; }
+    let mut cleaned = base.to_string();
+    if !kept.is_empty() { cleaned.push('?'); cleaned.push_str(&kept.join("&")); }
+    if let Some(f) = fragment { cleaned.push('#'); cleaned.push_str(f); }
+    Some(cleaned)
+}
+
+// WebKit's own entries that open new windows or duplicate ours.
+fn is_replaced(action: ContextMenuAction) -> bool {
+    matches!(action,
+        ContextMenuAction::OpenLinkInNewWindow | ContextMenuAction::CopyLinkToClipboard
+        | ContextMenuAction::OpenImageInNewWindow | ContextMenuAction::DownloadImageToDisk
+        | ContextMenuAction::OpenFrameInNewWindow | ContextMenuAction::OpenVideoInNewWindow
+        | ContextMenuAction::OpenAudioInNewWindow)
+}
+
+// Items are built fresh for each menu, so actions simply capture what was
+// under the pointer. WebKit collects them in one action group per menu, keyed
+// by name, so every item needs a name of its own.
+static NEXT_ITEM: AtomicU32 = AtomicU32::new(0);
+
+fn item(label: &str, activate: impl Fn() + 'static) -> ContextMenuItem {
+    let name = format!("page-menu-item-{}", NEXT_ITEM.fetch_add(1, Ordering::Relaxed));
+    let action = gio::SimpleAction::new(&name, None);
+    action.connect_activate(move |_, _| activate());
+    ContextMenuItem::from_gaction(&action, label, None)
+}
+
+fn open_tab(webview: &WebView, url: &str, incognito: bool, background: bool) {
+    if let Some(window) = BrowserWindow::for_new_tabs(webview) { window.open_tab(url, incognito, background); }
+}
+
+fn copy_text(webview: &WebView, text: &str) {
+    webview.clipboard().set_text(text);
+}
+
+fn search_selection(webview: &WebView, engine: String, incognito: bool) {
+    let wv = webview.downgrade();
+    webview.evaluate_javascript("window.getSelection().toString()", None, None, gio::Cancellable::NONE, move |result| {
+        let (wv, text) = match (wv.upgrade(), result) { (Some(wv), Ok(value)) => (wv, value.to_str().to_string()), _ => return };
+        let text = text.trim();
+        if text.is_empty() { return; }
+        open_tab(&wv, &format!("{}{}", engine, glib::Uri::escape_string(text, None, false)), incognito, false);
+    });
+}
+
+// The source as the server sent it, shown as plain text in a new tab.
+fn view_source(webview: &WebView, incognito: bool) {
+    let resource = match webview.main_resource() { Some(r) => r, None => return };
+    let wv = webview.downgrade();
+    resource.data(gio::Cancellable::NONE, move |result| {
+        let (wv, data) = match (wv.upgrade(), result) { (Some(wv), Ok(data)) => (wv, data), _ => return };
+        let window = match BrowserWindow::for_new_tabs(&wv) { Some(w) => w, None => return };
+        let tab = window.open_tab("", incognito, false);
+        tab.load_plain_text(&String::from_utf8_lossy(&data));
+    });
+}
+
+fn add_link_items(menu: &ContextMenu, webview: &WebView, browser: &Weak<Browser>, link: &str, incognito: bool) {
+    let mut items = Vec::new();
+    let (wv, url) = (webview.downgrade(), link.to_string());
+    items.push(item("Open Link in New Tab", move || {
+        if let Some(wv) = wv.upgrade() { open_tab(&wv, &url, incognito, true); }
+    }));
+    if !incognito {
+        let (wv, url) = (webview.downgrade(), link.to_string());
+        items.push(item("Open Link in Incognito Tab", move || {
+            if let Some(wv) = wv.upgrade() { open_tab(&wv, &url, true, true); }
+        }));
+    }
+    // The browser has no containers (tabs with their own cookie jar besides
+    // incognito), so there is no "Open Link in Container Tab"; a new window
+    // stands in for it.
+    let (browser, url) = (browser.clone(), link.to_string());
+    items.push(item("Open Link in New Window", move || {
+        let browser = match browser.upgrade() { Some(b) => b, None => return };
+        let window = browser.new_window();
+        window.create_tab(&url, incognito);
+        window.present();
+    }));
+    items.push(ContextMenuItem::new_separator());
+    let (wv, url) = (webview.downgrade(), link.to_string());
+    items.push(item("Copy Link", move || {
+        if let Some(wv) = wv.upgrade() { copy_text(&wv, &url); }
+    }));
+    if let Some(clean) = clean_link(link) {
+        let wv = webview.downgrade();
+        items.push(item("Copy Clean Link", move || {
+            if let Some(wv) = wv.upgrade() { copy_text(&wv, &clean); }
+        }));
+    }
+    items.push(ContextMenuItem::new_separator());
+    for (i, entry) in items.iter().enumerate() { menu.insert(entry, i as i32); }
+}
+
+// Browser entries around WebKit's: link actions first, then selection and
+// image actions, page source and the inspector last.
+pub fn attach(webview: &WebView, browser: Weak<Browser>, is_incognito: bool) {
+    webview.connect_context_menu(move |wv, menu, hit: &HitTestResult| {
+        let mut inspect = None;
+        for entry in menu.items() {
+            let action = entry.stock_action();
+            if action == ContextMenuAction::InspectElement { inspect = Some(entry.clone()); }
+            if is_replaced(action) || action == ContextMenuAction::InspectElement { menu.remove(&entry); }
+        }
+
+        if hit.context_is_link() {
+            if let Some(link) = hit.link_uri() { add_link_items(menu, wv, &browser, &link, is_incognito); }
+        }
+
+        if hit.context_is_selection() {
+            let engine = browser.upgrade().map(|b| {
+                let state = b.state.borrow();
+                let name = SEARCH_ENGINES.get(state.search_engine_index as usize).map_or("the Web", |(name, _)| *name);
+                (format!("Search {} for Selection", name), state.search_engine_url.clone())
+            });
+            if let Some((label, url)) = engine {
+                let wv = wv.downgrade();
+                menu.append(&ContextMenuItem::new_separator());
+                menu.append(&item(&label, move || {
+                    if let Some(wv) = wv.upgrade() { search_selection(&wv, url.clone(), is_incognito); }
+                }));
+            }
+        }
+
+        if hit.context_is_image() {
+            if let Some(image) = hit.image_uri() {
+                menu.append(&ContextMenuItem::new_separator());
+                let (w, url) = (wv.downgrade(), image.to_string());
+                menu.append(&item("Open Image in New Tab", move || {
+                    if let Some(wv) = w.upgrade() { open_tab(&wv, &url, is_incognito, true); }
+                }));
+                // Picked up by the session's download tracking like any other download.
+                let (w, url) = (wv.downgrade(), image.to_string());
+                menu.append(&item("Save Image", move || {
+                    if let Some(wv) = w.upgrade() { wv.download_uri(&url); }
+                }));
+            }
+        }
+
+        if !hit.context_is_editable() {
+            menu.append(&ContextMenuItem::new_separator());
+            let w = wv.downgrade();
+            menu.append(&item("View Page Source", move || {
+                if let Some(wv) = w.upgrade() { view_source(&wv, is_incognito); }
+            }));
+        }
+        // WebKit's own entry, moved last; it inspects the node under the pointer.
+        if let Some(inspect) = inspect { menu.append(&inspect); }
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clean_link;
+
+    #[test]
+    fn leaves_links_without_tracking_alone() {
+        assert_eq!(clean_link("https://example.com/page"), None);
+        assert_eq!(clean_link("https://example.com/page?"), None);
+        assert_eq!(clean_link("https://example.com/?q=rust&page=2"), None);
+        assert_eq!(clean_link("https://example.com/?utmost=1"), None);
+    }
+
+    #[test]
+    fn strips_utm_prefix_and_known_params() {
+        assert_eq!(clean_link("https://example.com/?q=rust&utm_source=x&utm_campaign=y").as_deref(), Some("https://example.com/?q=rust"));
+        assert_eq!(clean_link("https://example.com/?fbclid=abc&id=7").as_deref(), Some("https://example.com/?id=7"));
+    }
+
+    #[test]
+    fn drops_the_question_mark_when_nothing_is_left() {
+        assert_eq!(clean_link("https://example.com/a?utm_medium=email&gclid=1").as_deref(), Some("https://example.com/a"));
+    }
+
+    #[test]
+    fn keeps_the_fragment() {
+        assert_eq!(clean_link("https://example.com/?utm_source=x#top").as_deref(), Some("https://example.com/#top"));
+        assert_eq!(clean_link("https://example.com/#section?utm_source=x"), None);
+    }
+
+    #[test]
+    fn handles_params_without_values() {
+        assert_eq!(clean_link("https://example.com/?fbclid&flag").as_deref(), Some("https://example.com/?flag"));
+        assert_eq!(clean_link("https://example.com/?a=1&&utm_term").as_deref(), Some("https://example.com/?a=1"));
+    }
+}
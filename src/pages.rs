@@ -0,0 +1,80 @@
+// --- INTERNAL PAGES ---
+// Shared look for the HTML pages the browser renders itself.
+
+const PAGE_STYLE: &str = r#"
+    body { background: #1e1e20; color: #ececec; font-family: sans-serif; display: flex; justify-content: center; padding-top: 12vh; }
+    main { max-width: 560px; }
+    h1 { font-size: 22px; }
+    h1.danger { color: #ff5f56; }
+    p, li { color: #b0b0b0; line-height: 1.5; }
+    b, code { color: #ececec; }
+    details, .meta { margin-top: 24px; color: #808080; font-size: 13px; }
+    a.button { display: inline-block; margin-top: 16px; margin-right: 12px; padding: 8px 16px; border-radius: 6px; text-decoration: none; }
+    .primary { background: #3daee9; color: #1e1e20; }
+    .secondary { background: rgba(255, 255, 255, 0.08); color: #ececec; }
+    .danger-button { background: rgba(255, 80, 80, 0.15); color: #ff5f56; }
+"#;
+
+pub fn render(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title><style>{}</style></head>\n<body><main>\n{}\n</main></body></html>",
+        gtk::glib::markup_escape_text(title),
+        PAGE_STYLE,
+        body
+    )
+}
+
+const APP_STYLE: &str = r#"
+    body { padding-top: 4vh; }
+    main { max-width: 820px; width: 100%; }
+    h1 { display: flex; align-items: center; gap: 12px; }
+    h2 { font-size: 15px; color: #808080; text-transform: uppercase; letter-spacing: 0.05em; margin-top: 32px; }
+    a { color: #3daee9; }
+    input[type=text], input[type=search], select { background: #2a2a2c; color: #ececec; border: 1px solid #3a3a3c; border-radius: 8px; padding: 6px 10px; font-size: 14px; }
+    input[type=search] { width: 100%; box-sizing: border-box; margin-bottom: 12px; }
+    button { background: rgba(255, 255, 255, 0.05); color: #ececec; border: none; border-radius: 5px; padding: 5px 10px; cursor: pointer; }
+    button:hover { background: rgba(255, 255, 255, 0.1); }
+    .row { display: flex; align-items: center; gap: 12px; padding: 8px 4px; border-bottom: 1px solid #2a2a2c; }
+    .row .grow { flex: 1; min-width: 0; }
+    .row .title { color: #ececec; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+    .row .url { color: #808080; font-size: 12px; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+    .setting { display: flex; align-items: center; justify-content: space-between; padding: 8px 0; }
+    .empty { color: #808080; font-style: italic; }
+    .fav { position: relative; flex: none; width: 16px; height: 16px; border-radius: 50%; background: #3daee9; color: #1e1e20; font-size: 10px; font-weight: bold; display: flex; align-items: center; justify-content: center; }
+    .fav img { position: absolute; inset: 0; width: 100%; height: 100%; background: #1e1e20; }
+"#;
+
+// Internal rusty: pages. send() is the only way back into the browser: the
+// message handler lives in an isolated script world, which only listens for
+// these events on rusty: pages (see internal::attach).
+const APP_SCRIPT: &str = r#"
+    function send(msg) { document.dispatchEvent(new CustomEvent("rusty-send", { detail: JSON.stringify(msg) })); }
+    function filterRows(query) {
+        query = query.toLowerCase();
+        for (const row of document.querySelectorAll(".row[data-filter]")) {
+            row.style.display = row.dataset.filter.toLowerCase().includes(query) ? "" : "none";
+        }
+    }
+"#;
+
+pub fn render_app(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{}</title><style>{}{}</style><script>{}</script></head>\n<body><main>\n{}\n</main></body></html>",
+        gtk::glib::markup_escape_text(title),
+        PAGE_STYLE,
+        APP_STYLE,
+        APP_SCRIPT,
+        body
+    )
+}
+
+// The letter sits under the image; a missing favicon just removes the image.
+pub fn favicon(url: &str) -> String {
+    format!(r#"<span class="fav">{letter}<img src="rusty:favicon?url={src}" alt="" onerror="this.remove()"></span>"#,
+        letter = escape(&crate::favicons::letter_for(url)),
+        src = escape(&gtk::glib::Uri::escape_string(url, None, false)))
+}
+
+pub fn escape(text: &str) -> String {
+    gtk::glib::markup_escape_text(text).to_string()
+}
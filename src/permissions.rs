@@ -0,0 +1,495 @@
+// --- SITE PERMISSIONS ---
+use gtk::prelude::*;
+use gtk::{glib, Align, Box, Button, DropDown, InfoBar, Label, ListBox, MessageType, Notebook, Orientation, PolicyType, Popover, ResponseType, ScrolledWindow, SelectionMode, Separator, StringList, Switch, Window};
+use webkit6::prelude::*;
+use webkit6::{AutoplayPolicy, GeolocationPermissionRequest, NotificationPermissionRequest, PermissionRequest, SecurityOrigin, UserContentInjectedFrames, UserContentManager,
+    UserMediaPermissionRequest, UserScript, UserScriptInjectionTime, WebView, WebsitePolicies};
+use serde::{Serialize, Deserialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::notifications::NotificationBridge;
+use crate::tab_page;
+use crate::{load_json, save_json};
+
+const PERMISSIONS_FILE: &str = "permissions.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum PermissionKind {
+    Geolocation,
+    Camera,
+    Microphone,
+    Notifications,
+    Clipboard,
+    Autoplay,
+    Popups,
+}
+
+pub const ALL_KINDS: [PermissionKind; 7] = [
+    PermissionKind::Geolocation,
+    PermissionKind::Camera,
+    PermissionKind::Microphone,
+    PermissionKind::Notifications,
+    PermissionKind::Clipboard,
+    PermissionKind::Autoplay,
+    PermissionKind::Popups,
+];
+
+impl PermissionKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            PermissionKind::Geolocation => "Location",
+            PermissionKind::Camera => "Camera",
+            PermissionKind::Microphone => "Microphone",
+            PermissionKind::Notifications => "Notifications",
+            PermissionKind::Clipboard => "Clipboard",
+            PermissionKind::Autoplay => "Autoplay",
+            PermissionKind::Popups => "Pop-ups",
+        }
+    }
+
+    fn verb(self) -> &'static str {
+        match self {
+            PermissionKind::Geolocation => "know your location",
+            PermissionKind::Camera => "use your camera",
+            PermissionKind::Microphone => "use your microphone",
+            PermissionKind::Notifications => "show notifications",
+            PermissionKind::Clipboard => "read your clipboard",
+            PermissionKind::Autoplay => "play media automatically",
+            PermissionKind::Popups => "open pop-up windows",
+        }
+    }
+}
+
+// origin -> kind -> allowed. A missing entry means "ask".
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct SitePermissions {
+    sites: BTreeMap<String, BTreeMap<PermissionKind, bool>>,
+}
+
+impl SitePermissions {
+    pub fn load() -> Self {
+        load_json(PERMISSIONS_FILE)
+    }
+
+    pub fn save(&self) {
+        save_json(PERMISSIONS_FILE, self);
+    }
+
+    pub fn get(&self, origin: &str, kind: PermissionKind) -> Option<bool> {
+        self.sites.get(origin).and_then(|kinds| kinds.get(&kind).copied())
+    }
+
+    pub fn set(&mut self, origin: &str, kind: PermissionKind, decision: Option<bool>) {
+        match decision {
+            Some(allowed) => { self.sites.entry(origin.to_string()).or_default().insert(kind, allowed); }
+            None => {
+                if let Some(kinds) = self.sites.get_mut(origin) { kinds.remove(&kind); }
+                self.sites.retain(|_, kinds| !kinds.is_empty());
+            }
+        }
+    }
+
+    pub fn revoke_all(&mut self, origin: &str) {
+        self.sites.remove(origin);
+    }
+
+    pub fn origins_with(&self, kind: PermissionKind, allowed: bool) -> Vec<String> {
+        self.sites.iter().filter(|(_, kinds)| kinds.get(&kind) == Some(&allowed)).map(|(origin, _)| origin.clone()).collect()
+    }
+}
+
+pub type SharedPermissions = Rc<RefCell<SitePermissions>>;
+
+pub fn origin_of(uri: &str) -> Option<String> {
+    let parsed = glib::Uri::parse(uri, glib::UriFlags::NONE).ok()?;
+    let scheme = parsed.scheme();
+    let host = parsed.host()?;
+    if host.is_empty() { return None; }
+    Some(match parsed.port() {
+        -1 => format!("{}://{}", scheme, host),
+        port => format!("{}://{}:{}", scheme, host, port),
+    })
+}
+
+// Tells WebKit which origins already decided on notifications, so
+// Notification.permission is right before any prompt.
+pub fn notification_origins(store: &SitePermissions) -> (Vec<SecurityOrigin>, Vec<SecurityOrigin>) {
+    let to_origins = |list: Vec<String>| list.iter().map(|o| SecurityOrigin::for_uri(o)).collect();
+    (
+        to_origins(store.origins_with(PermissionKind::Notifications, true)),
+        to_origins(store.origins_with(PermissionKind::Notifications, false)),
+    )
+}
+
+pub fn autoplay_policies(store: &SitePermissions, uri: &str) -> Option<WebsitePolicies> {
+    let origin = origin_of(uri)?;
+    let policy = match store.get(&origin, PermissionKind::Autoplay)? {
+        true => AutoplayPolicy::Allow,
+        false => AutoplayPolicy::Deny,
+    };
+    Some(glib::Object::builder().property("autoplay", policy).build())
+}
+
+// --- AUTOPLAY PROMPT ---
+// WebKit blocks autoplay without telling anyone, so pages are watched for it:
+// a play() refused for want of a click, or an autoplay element left paused.
+const AUTOPLAY_WORLD: &str = "rusty-autoplay";
+const AUTOPLAY_HANDLER: &str = "autoplayBlocked";
+
+// Page world: only the page's own play() sees the refusal.
+const AUTOPLAY_HOOK: &str = r#"
+(() => {
+    const play = HTMLMediaElement.prototype.play;
+    HTMLMediaElement.prototype.play = function () {
+        const result = play.apply(this, arguments);
+        result.catch(e => { if (e && e.name === "NotAllowedError") document.dispatchEvent(new Event("rusty-autoplay-blocked")); });
+        return result;
+    };
+})();
+"#;
+
+// AUTOPLAY_WORLD: reports the first blocked attempt on each page.
+const AUTOPLAY_WATCH: &str = r#"
+(() => {
+    let told = false;
+    const tell = () => { if (!told) { told = true; window.webkit.messageHandlers.autoplayBlocked.postMessage(""); } };
+    document.addEventListener("rusty-autoplay-blocked", tell);
+    document.addEventListener("canplay", e => {
+        const media = e.target;
+        if (media instanceof HTMLMediaElement && media.autoplay) setTimeout(() => { if (media.paused) tell(); }, 0);
+    }, true);
+})();
+"#;
+
+pub fn watch_autoplay(manager: &UserContentManager, on_blocked: impl Fn() + 'static) {
+    manager.add_script(&UserScript::new(AUTOPLAY_HOOK, UserContentInjectedFrames::TopFrame, UserScriptInjectionTime::Start, &[], &[]));
+    manager.add_script(&UserScript::for_world(AUTOPLAY_WATCH, UserContentInjectedFrames::TopFrame, UserScriptInjectionTime::Start, AUTOPLAY_WORLD, &[], &[]));
+    manager.register_script_message_handler(AUTOPLAY_HANDLER, Some(AUTOPLAY_WORLD));
+    manager.connect_script_message_received(Some(AUTOPLAY_HANDLER), move |_, _| on_blocked());
+}
+
+// Only for sites without a decision. The choice is saved and the page
+// reloaded, since the autoplay policy is set per navigation.
+pub fn offer_autoplay(webview: &WebView, store: &SharedPermissions, prompts: &Rc<PermissionPrompts>) {
+    let origin = match webview.uri().and_then(|u| origin_of(&u)) { Some(o) => o, None => return };
+    if store.borrow().get(&origin, PermissionKind::Autoplay).is_some() { return; }
+    let text = format!("{} wants to {}.", origin, PermissionKind::Autoplay.verb());
+    let (store, wv) = (store.clone(), webview.downgrade());
+    prompts.show(webview, &text, move |answer| {
+        let allowed = match answer { Some(a) => a, None => return };
+        {
+            let mut store = store.borrow_mut();
+            store.set(&origin, PermissionKind::Autoplay, Some(allowed));
+            store.save();
+        }
+        if let (true, Some(wv)) = (allowed, wv.upgrade()) { wv.reload(); }
+    });
+}
+
+fn request_kinds(request: &PermissionRequest) -> Vec<PermissionKind> {
+    if request.is::<GeolocationPermissionRequest>() {
+        vec![PermissionKind::Geolocation]
+    } else if request.is::<NotificationPermissionRequest>() {
+        vec![PermissionKind::Notifications]
+    } else if request.is::<UserMediaPermissionRequest>() {
+        let mut kinds = Vec::new();
+        if request.property::<bool>("is-for-video-device") { kinds.push(PermissionKind::Camera); }
+        if request.property::<bool>("is-for-audio-device") { kinds.push(PermissionKind::Microphone); }
+        kinds
+    } else if request.type_().name() == "WebKitClipboardPermissionRequest" {
+        // Only in newer WebKitGTK releases than the bindings cover.
+        vec![PermissionKind::Clipboard]
+    } else {
+        Vec::new()
+    }
+}
+
+// Returns true when the request was taken over (answered or prompted).
+// Anything we don't recognise falls through to WebKit, which denies it.
+pub fn handle_request(webview: &WebView, request: &PermissionRequest, store: &SharedPermissions, prompts: &Rc<PermissionPrompts>, remember: bool) -> bool {
+    let kinds = request_kinds(request);
+    if kinds.is_empty() { return false; }
+    let origin = match webview.uri().and_then(|u| origin_of(&u)) {
+        Some(o) => o,
+        None => { request.deny(); return true; }
+    };
+
+    let decisions: Vec<Option<bool>> = kinds.iter().map(|k| store.borrow().get(&origin, *k)).collect();
+    if decisions.contains(&Some(false)) { request.deny(); return true; }
+    if decisions.iter().all(|d| *d == Some(true)) { request.allow(); return true; }
+
+    let verbs: Vec<&str> = kinds.iter().map(|k| k.verb()).collect();
+    let text = format!("{} wants to {}.", origin, verbs.join(" and "));
+    let request = request.clone();
+    let store = store.clone();
+    prompts.show(webview, &text, move |answer| {
+        if let Some(allowed) = answer {
+            if remember {
+                let mut store = store.borrow_mut();
+                for kind in &kinds { store.set(&origin, *kind, Some(allowed)); }
+                store.save();
+            }
+        }
+        if answer == Some(true) { request.allow(); } else { request.deny(); }
+    });
+    true
+}
+
+// Non-modal prompts stacked above the notebook. Each one belongs to a tab and
+// is only visible while that tab is selected.
+pub struct PermissionPrompts {
+    area: Box,
+    // None for a pop-up window, whose one page owns every prompt.
+    notebook: Option<glib::WeakRef<Notebook>>,
+    entries: RefCell<Vec<(glib::WeakRef<WebView>, InfoBar, String)>>,
+}
+
+impl PermissionPrompts {
+    pub fn new(notebook: &Notebook) -> Rc<Self> {
+        Rc::new(Self { area: Box::new(Orientation::Vertical, 0), notebook: Some(notebook.downgrade()), entries: RefCell::new(Vec::new()) })
+    }
+
+    pub fn standalone() -> Rc<Self> {
+        Rc::new(Self { area: Box::new(Orientation::Vertical, 0), notebook: None, entries: RefCell::new(Vec::new()) })
+    }
+
+    pub fn widget(&self) -> &Box {
+        &self.area
+    }
+
+    fn show(self: &Rc<Self>, webview: &WebView, text: &str, on_answer: impl FnOnce(Option<bool>) + 'static) {
+        // A page repeating itself (e.g. pop-up spam) gets one prompt, not a stack;
+        // the repeats count as dismissed.
+        if self.entries.borrow().iter().any(|(wv, _, t)| t == text && wv.upgrade().as_ref() == Some(webview)) { return on_answer(None); }
+        let bar = InfoBar::new();
+        bar.set_message_type(MessageType::Question);
+        bar.set_show_close_button(true);
+        let label = Label::new(Some(text)); label.set_wrap(true); label.set_halign(Align::Start);
+        bar.add_child(&label);
+        bar.add_button("Allow", ResponseType::Accept);
+        bar.add_button("Block", ResponseType::Reject);
+
+        let on_answer = RefCell::new(Some(on_answer));
+        let this = Rc::downgrade(self);
+        bar.connect_response(move |bar, response| {
+            let answer = match response {
+                ResponseType::Accept => Some(true),
+                ResponseType::Reject => Some(false),
+                _ => None,
+            };
+            if let Some(f) = on_answer.borrow_mut().take() { f(answer); }
+            if let Some(this) = this.upgrade() {
+                this.area.remove(bar);
+                this.entries.borrow_mut().retain(|(_, b, _)| b != bar);
+            }
+        });
+
+        self.area.append(&bar);
+        self.entries.borrow_mut().push((webview.downgrade(), bar, text.to_string()));
+        self.sync();
+    }
+
+    // Pending prompts die with the page that asked.
+    pub fn dismiss(&self, webview: &WebView) {
+        let bars: Vec<InfoBar> = self.entries.borrow().iter().filter(|(wv, _, _)| wv.upgrade().as_ref() == Some(webview)).map(|(_, b, _)| b.clone()).collect();
+        for bar in bars { bar.response(ResponseType::Close); }
+    }
+
+    pub fn sync(&self) {
+        let current = self.notebook.as_ref()
+            .map(|nb| nb.upgrade().and_then(|nb| nb.nth_page(nb.current_page())).and_then(|page| tab_page::webview(&page)));
+        for (wv, bar, _) in self.entries.borrow().iter() {
+            let visible = match (wv.upgrade(), &current) {
+                (Some(wv), Some(current)) => current.as_ref() == Some(&wv),
+                (Some(_), None) => true,
+                _ => false,
+            };
+            bar.set_visible(visible);
+        }
+    }
+}
+
+// A pop-up the user clicked for always opens. A saved decision only covers
+// the ones they didn't, which are blocked by default.
+pub fn popup_allowed(store: &SitePermissions, opener_uri: Option<&str>, user_gesture: bool) -> bool {
+    user_gesture || opener_uri.and_then(origin_of).and_then(|o| store.get(&o, PermissionKind::Popups)).unwrap_or(false)
+}
+
+// Offered when a pop-up was blocked by the default rule. Allowing opens what
+// was blocked and, outside incognito, lets the site open pop-ups from now on.
+// `answered` hears true for Allow and false for anything else.
+pub fn offer_blocked_popup(webview: &WebView, store: &SharedPermissions, prompts: &Rc<PermissionPrompts>, remember: bool, answered: impl FnOnce(bool) + 'static) {
+    let origin = match webview.uri().and_then(|u| origin_of(&u)) { Some(o) => o, None => return answered(false) };
+    let text = format!("{} tried to open a pop-up window.", origin);
+    let store = store.clone();
+    prompts.show(webview, &text, move |answer| {
+        if let (Some(allowed), true) = (answer, remember) {
+            let mut store = store.borrow_mut();
+            store.set(&origin, PermissionKind::Popups, Some(allowed));
+            store.save();
+        }
+        answered(answer == Some(true));
+    });
+}
+
+fn decision_dropdown(kind: PermissionKind, decision: Option<bool>) -> DropDown {
+    let ask = if matches!(kind, PermissionKind::Autoplay | PermissionKind::Popups) { "Default" } else { "Ask" };
+    let dropdown = DropDown::new(Some(StringList::new(&[ask, "Allow", "Block"])), Option::<gtk::Expression>::None);
+    dropdown.set_selected(match decision { None => 0, Some(true) => 1, Some(false) => 2 });
+    dropdown
+}
+
+fn decision_from_index(idx: u32) -> Option<bool> {
+    match idx { 1 => Some(true), 2 => Some(false), _ => None }
+}
+
+// Filled in each time the page-info popover opens.
+pub fn fill_page_info(popover: &Popover, uri: Option<String>, store: &SharedPermissions, notifications: &Rc<NotificationBridge>) {
+    let vbox = Box::new(Orientation::Vertical, 8);
+    vbox.set_margin_top(10); vbox.set_margin_bottom(10); vbox.set_margin_start(10); vbox.set_margin_end(10);
+
+    let origin = match uri.as_deref().and_then(origin_of) {
+        Some(o) => o,
+        None => {
+            vbox.append(&Label::new(Some("No site information for this page.")));
+            popover.set_child(Some(&vbox));
+            return;
+        }
+    };
+
+    let title = Label::new(None); title.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(&origin))); title.set_halign(Align::Start); vbox.append(&title);
+    vbox.append(&Separator::new(Orientation::Horizontal));
+    let label_perms = Label::new(None); label_perms.set_markup("<b>Permissions</b>"); label_perms.set_halign(Align::Start); vbox.append(&label_perms);
+
+    for kind in ALL_KINDS {
+        let row = Box::new(Orientation::Horizontal, 10);
+        let name = Label::new(Some(kind.label())); name.set_hexpand(true); name.set_halign(Align::Start);
+        let dropdown = decision_dropdown(kind, store.borrow().get(&origin, kind));
+        row.append(&name);
+        row.append(&dropdown);
+        vbox.append(&row);
+
+        let store = store.clone();
+        let origin = origin.clone();
+        dropdown.connect_selected_notify(move |dd| {
+            let mut store = store.borrow_mut();
+            store.set(&origin, kind, decision_from_index(dd.selected()));
+            store.save();
+        });
+    }
+
+    let mute_switch = Switch::new(); mute_switch.set_active(notifications.is_muted(&origin));
+    let mute_box = Box::new(Orientation::Horizontal, 10); mute_box.append(&mute_switch); mute_box.append(&Label::new(Some("Mute Notifications"))); vbox.append(&mute_box);
+    let notifications_mute = notifications.clone();
+    let origin_mute = origin.clone();
+    mute_switch.connect_active_notify(move |sw| notifications_mute.set_muted(&origin_mute, sw.is_active()));
+
+    let reset_btn = Button::builder().label("Reset Permissions").build();
+    reset_btn.add_css_class("flat-button");
+    vbox.append(&reset_btn);
+    let store_reset = store.clone();
+    let popover_weak = popover.downgrade();
+    reset_btn.connect_clicked(move |_| {
+        let mut store = store_reset.borrow_mut();
+        store.revoke_all(&origin);
+        store.save();
+        if let Some(p) = popover_weak.upgrade() { p.popdown(); }
+    });
+
+    popover.set_child(Some(&vbox));
+}
+
+fn refresh_site_list(list: &ListBox, store: &SharedPermissions) {
+    while let Some(child) = list.first_child() { list.remove(&child); }
+    let sites: Vec<(String, BTreeMap<PermissionKind, bool>)> = store.borrow().sites.iter().map(|(o, k)| (o.clone(), k.clone())).collect();
+
+    for (origin, kinds) in sites {
+        let vbox = Box::new(Orientation::Vertical, 4);
+        vbox.set_margin_top(6); vbox.set_margin_bottom(6); vbox.set_margin_start(6); vbox.set_margin_end(6);
+        let header = Box::new(Orientation::Horizontal, 10);
+        let name = Label::new(None); name.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(&origin))); name.set_hexpand(true); name.set_halign(Align::Start);
+        let revoke_all = Button::builder().label("Revoke All").build(); revoke_all.add_css_class("flat-button");
+        header.append(&name);
+        header.append(&revoke_all);
+        vbox.append(&header);
+
+        for (kind, allowed) in kinds {
+            let row = Box::new(Orientation::Horizontal, 10);
+            let lbl = Label::new(Some(&format!("{}: {}", kind.label(), if allowed { "Allowed" } else { "Blocked" })));
+            lbl.set_hexpand(true); lbl.set_halign(Align::Start);
+            let revoke = Button::builder().icon_name("list-remove-symbolic").tooltip_text("Revoke").build();
+            row.append(&lbl);
+            row.append(&revoke);
+            vbox.append(&row);
+
+            let store = store.clone();
+            let origin = origin.clone();
+            let list_weak = list.downgrade();
+            revoke.connect_clicked(move |_| {
+                store.borrow_mut().set(&origin, kind, None);
+                store.borrow().save();
+                if let Some(list) = list_weak.upgrade() { refresh_site_list(&list, &store); }
+            });
+        }
+
+        let store_all = store.clone();
+        let list_weak = list.downgrade();
+        revoke_all.connect_clicked(move |_| {
+            store_all.borrow_mut().revoke_all(&origin);
+            store_all.borrow().save();
+            if let Some(list) = list_weak.upgrade() { refresh_site_list(&list, &store_all); }
+        });
+
+        list.append(&vbox);
+    }
+}
+
+pub fn show_permissions_window(parent: &impl IsA<Window>, store: &SharedPermissions) {
+    let window = Window::builder().transient_for(parent).title("Site Permissions").default_width(480).default_height(500).build();
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_top(20); vbox.set_margin_bottom(20); vbox.set_margin_start(20); vbox.set_margin_end(20);
+    let label = Label::new(None); label.set_markup("<b>Sites With Saved Permissions</b>"); label.set_halign(Align::Start); vbox.append(&label);
+
+    let list = ListBox::new();
+    list.set_selection_mode(SelectionMode::None);
+    list.set_placeholder(Some(&Label::new(Some("No saved permissions."))));
+    let scroll = ScrolledWindow::builder().hscrollbar_policy(PolicyType::Never).vexpand(true).child(&list).build();
+    vbox.append(&scroll);
+
+    refresh_site_list(&list, store);
+    window.set_child(Some(&vbox));
+    window.present();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{origin_of, popup_allowed, PermissionKind, SitePermissions};
+
+    #[test]
+    fn origin_keeps_scheme_host_and_explicit_port() {
+        assert_eq!(origin_of("https://example.com/a?b#c").as_deref(), Some("https://example.com"));
+        assert_eq!(origin_of("http://localhost:8000/x").as_deref(), Some("http://localhost:8000"));
+        assert_eq!(origin_of("about:blank"), None);
+    }
+
+    #[test]
+    fn clicked_pop_ups_always_open() {
+        let mut store = SitePermissions::default();
+        store.set("https://example.com", PermissionKind::Popups, Some(false));
+        assert!(popup_allowed(&store, Some("https://example.com/page"), true));
+        assert!(popup_allowed(&store, None, true));
+    }
+
+    #[test]
+    fn unclicked_pop_ups_follow_the_saved_decision() {
+        let mut store = SitePermissions::default();
+        assert!(!popup_allowed(&store, Some("https://example.com/page"), false));
+        store.set("https://example.com", PermissionKind::Popups, Some(true));
+        assert!(popup_allowed(&store, Some("https://example.com/page"), false));
+        store.set("https://example.com", PermissionKind::Popups, Some(false));
+        assert!(!popup_allowed(&store, Some("https://example.com/page"), false));
+        assert!(!popup_allowed(&store, Some("https://other.example/"), false));
+    }
+}
@@ -0,0 +1,155 @@
+// --- SESSION ---
+use gtk::prelude::*;
+use gtk::Notebook;
+use serde::{Serialize, Deserialize};
+
+use crate::tab_groups::{TabGroup, TabGroups};
+use crate::tab_label;
+use crate::{load_json, save_json};
+
+const SESSION_FILE: &str = "session.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedTab {
+    pub url: String,
+    pub title: String,
+    pub pinned: bool,
+    #[serde(default)]
+    pub group: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct WindowSession {
+    pub tabs: Vec<SavedTab>,
+    pub active: u32,
+    pub groups: Vec<TabGroup>,
+}
+
+// Incognito tabs are never written; amnesia sessions write nothing at all.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Session {
+    pub windows: Vec<WindowSession>,
+}
+
+impl Session {
+    pub fn load() -> Self {
+        let session: Session = load_json(SESSION_FILE);
+        if !session.windows.is_empty() { return session; }
+        Self::from_single(load_json(SESSION_FILE))
+    }
+
+    // Older files held a single window at the top level.
+    fn from_single(single: WindowSession) -> Self {
+        Session { windows: if single.tabs.is_empty() { Vec::new() } else { vec![single] } }
+    }
+
+    pub fn save(&self) {
+        save_json(SESSION_FILE, self);
+    }
+}
+
+impl WindowSession {
+    pub fn capture(notebook: &Notebook, groups: &TabGroups) -> Self {
+        let mut session = WindowSession { groups: groups.groups(), ..WindowSession::default() };
+        for i in 0..notebook.n_pages() {
+            let header = match notebook.nth_page(Some(i)).and_then(|p| tab_label::header(&p)) { Some(h) => h, None => continue };
+            if header.is_incognito() { continue; }
+            let url = header.uri();
+            if url.is_empty() { continue; }
+            if notebook.current_page() == Some(i) { session.active = session.tabs.len() as u32; }
+            session.tabs.push(SavedTab {
+                url,
+                title: header.title(),
+                pinned: header.is_pinned(),
+                group: header.group(),
+            });
+        }
+        session
+    }
+
+    // Pinned tabs come back on every start; the rest only when asked to.
+    // Groups only come back with the tabs in them.
+    pub fn startup_tabs(self, restore_all: bool) -> (Vec<SavedTab>, Vec<TabGroup>, Option<usize>) {
+        if restore_all {
+            let active = self.tabs.get(self.active as usize).map(|_| self.active as usize);
+            (self.tabs, self.groups, active)
+        } else {
+            (self.tabs.into_iter().filter(|t| t.pinned).collect(), Vec::new(), None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SavedTab, Session, WindowSession};
+    use crate::tab_groups::TabGroup;
+
+    fn tab(url: &str, pinned: bool) -> SavedTab {
+        SavedTab { url: url.to_string(), title: String::new(), pinned, group: None }
+    }
+
+    fn window(tabs: Vec<SavedTab>, active: u32) -> WindowSession {
+        WindowSession { tabs, active, ..WindowSession::default() }
+    }
+
+    #[test]
+    fn pinned_tabs_always_come_back() {
+        let saved = window(vec![tab("https://a/", true), tab("https://b/", false), tab("https://c/", true)], 1);
+        let (tabs, _, active) = saved.startup_tabs(false);
+        let urls: Vec<&str> = tabs.iter().map(|t| t.url.as_str()).collect();
+        assert_eq!(urls, ["https://a/", "https://c/"]);
+        assert_eq!(active, None);
+    }
+
+    #[test]
+    fn restoring_keeps_order_and_selection() {
+        let saved = window(vec![tab("https://a/", true), tab("https://b/", false), tab("https://c/", false)], 2);
+        let (tabs, _, active) = saved.startup_tabs(true);
+        let urls: Vec<&str> = tabs.iter().map(|t| t.url.as_str()).collect();
+        assert_eq!(urls, ["https://a/", "https://b/", "https://c/"]);
+        assert_eq!(active, Some(2));
+    }
+
+    #[test]
+    fn a_stale_selection_is_dropped() {
+        let (_, _, active) = window(vec![tab("https://a/", false)], 7).startup_tabs(true);
+        assert_eq!(active, None);
+    }
+
+    #[test]
+    fn groups_only_come_back_with_their_tabs() {
+        let mut saved = window(vec![tab("https://a/", true), tab("https://b/", false)], 0);
+        saved.tabs[1].group = Some(1);
+        saved.groups.push(TabGroup { id: 1, name: "Work".to_string(), color: 0, collapsed: true });
+        let (_, groups, _) = saved.startup_tabs(false);
+        assert!(groups.is_empty());
+
+        let mut saved = window(vec![tab("https://b/", false)], 0);
+        saved.groups.push(TabGroup { id: 1, name: "Work".to_string(), color: 0, collapsed: true });
+        let (_, groups, _) = saved.startup_tabs(true);
+        assert_eq!(groups.len(), 1);
+    }
+
+    #[test]
+    fn tabs_saved_before_groups_still_load() {
+        let saved: SavedTab = serde_json::from_str(r#"{"url": "https://a/", "title": "A", "pinned": false}"#).unwrap();
+        assert_eq!(saved.group, None);
+    }
+
+    #[test]
+    fn single_window_files_still_load() {
+        let old = r#"{"tabs": [{"url": "https://a/", "title": "A", "pinned": true}], "active": 0}"#;
+        let session: Session = serde_json::from_str(old).unwrap();
+        assert!(session.windows.is_empty());
+        let session = Session::from_single(serde_json::from_str(old).unwrap());
+        assert_eq!(session.windows.len(), 1);
+        assert_eq!(session.windows[0].tabs[0].url, "https://a/");
+    }
+
+    #[test]
+    fn an_empty_file_restores_no_windows() {
+        assert!(Session::from_single(WindowSession::default()).windows.is_empty());
+    }
+}
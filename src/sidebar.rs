@@ -0,0 +1,235 @@
+// --- VERTICAL TABS ---
+use gtk::prelude::*;
+use gtk::{glib, pango, Align, Box, Button, Label, ListBox, ListBoxRow, Notebook, Orientation, PolicyType, ScrolledWindow, SelectionMode, Widget};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::favicons;
+use crate::tab_groups::{TabGroups, COLORS};
+use crate::tab_label;
+
+const EXPANDED_WIDTH: i32 = 240;
+
+enum Row {
+    Tab(Widget),
+    Group(u32),
+}
+
+// What a tab row shows: its title, else its address, else "New Tab".
+fn row_title(title: &str, uri: &str) -> String {
+    [title, uri].into_iter().find(|t| !t.is_empty()).unwrap_or("New Tab").to_string()
+}
+
+// A collapsed sidebar only has room for the group's first letter.
+fn chip_name(name: &str, collapsed: bool) -> String {
+    if collapsed { name.chars().take(1).collect() } else { name.to_string() }
+}
+
+// A list mirror of the notebook, shown instead of its tab strip. The notebook
+// still owns the pages; this only reads it and switches/closes through it.
+pub struct TabSidebar {
+    root: Box,
+    list: ListBox,
+    notebook: glib::WeakRef<Notebook>,
+    groups: Rc<TabGroups>,
+    rows: RefCell<Vec<Row>>,
+    collapsed: Cell<bool>,
+    rebuild_pending: Cell<bool>,
+    syncing: Cell<bool>,
+}
+
+impl TabSidebar {
+    pub fn new(notebook: &Notebook, groups: Rc<TabGroups>, collapsed: bool, on_collapse: impl Fn(bool) + 'static) -> Rc<Self> {
+        let root = Box::new(Orientation::Vertical, 0);
+        root.add_css_class("tab-sidebar");
+
+        let collapse_btn = Button::builder().icon_name("sidebar-show-symbolic").tooltip_text("Collapse Sidebar").halign(Align::End).build();
+        root.append(&collapse_btn);
+
+        let list = ListBox::new();
+        list.set_selection_mode(SelectionMode::Single);
+        let scroll = ScrolledWindow::builder().hscrollbar_policy(PolicyType::Never).vexpand(true).child(&list).build();
+        root.append(&scroll);
+
+        let this = Rc::new(Self {
+            root,
+            list,
+            notebook: notebook.downgrade(),
+            groups,
+            rows: RefCell::new(Vec::new()),
+            collapsed: Cell::new(collapsed),
+            rebuild_pending: Cell::new(false),
+            syncing: Cell::new(false),
+        });
+        this.apply_width();
+
+        let weak = Rc::downgrade(&this);
+        collapse_btn.connect_clicked(move |_| {
+            if let Some(this) = weak.upgrade() {
+                this.collapsed.set(!this.collapsed.get());
+                this.apply_width();
+                on_collapse(this.collapsed.get());
+                this.schedule_rebuild();
+            }
+        });
+
+        let weak = Rc::downgrade(&this);
+        this.list.connect_row_activated(move |_, row| {
+            if let Some(this) = weak.upgrade() { this.toggle_group(row.index()); }
+        });
+        let weak = Rc::downgrade(&this);
+        this.list.connect_row_selected(move |_, row| {
+            if let (Some(this), Some(row)) = (weak.upgrade(), row) {
+                if !this.syncing.get() { this.select_tab(row.index()); }
+            }
+        });
+
+        let weak = Rc::downgrade(&this);
+        notebook.connect_page_added(move |_, page, _| {
+            let this = match weak.upgrade() { Some(t) => t, None => return };
+            this.watch(page);
+            this.schedule_rebuild();
+        });
+        for signal in ["page-removed", "page-reordered", "switch-page"] {
+            let weak = Rc::downgrade(&this);
+            notebook.connect_local(signal, false, move |_| {
+                if let Some(this) = weak.upgrade() { this.schedule_rebuild(); }
+                None
+            });
+        }
+        this
+    }
+
+    pub fn widget(&self) -> &Box {
+        &self.root
+    }
+
+    // Vertical mode hides the notebook's own tab strip.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.root.set_visible(enabled);
+        if let Some(nb) = self.notebook.upgrade() { nb.set_show_tabs(!enabled); }
+        if enabled { self.schedule_rebuild(); }
+    }
+
+    fn apply_width(&self) {
+        self.root.set_size_request(if self.collapsed.get() { -1 } else { EXPANDED_WIDTH }, -1);
+    }
+
+    fn watch(self: &Rc<Self>, page: &Widget) {
+        let weak = Rc::downgrade(self);
+        page.connect_visible_notify(move |_| {
+            if let Some(this) = weak.upgrade() { this.schedule_rebuild(); }
+        });
+        let weak = Rc::downgrade(self);
+        if let Some(header) = tab_label::header(page) {
+            header.connect_changed(move || {
+                if let Some(this) = weak.upgrade() { this.schedule_rebuild(); }
+            });
+        }
+    }
+
+    // Coalesces bursts of notebook/webview signals into one rebuild.
+    fn schedule_rebuild(self: &Rc<Self>) {
+        if self.rebuild_pending.replace(true) { return; }
+        let weak = Rc::downgrade(self);
+        glib::idle_add_local_once(move || {
+            if let Some(this) = weak.upgrade() {
+                this.rebuild_pending.set(false);
+                if this.root.is_visible() { this.rebuild(); }
+            }
+        });
+    }
+
+    fn select_tab(&self, index: i32) {
+        if let (Some(notebook), Some(Row::Tab(page))) = (self.notebook.upgrade(), self.rows.borrow().get(index as usize)) {
+            notebook.set_current_page(notebook.page_num(page));
+        }
+    }
+
+    fn toggle_group(&self, index: i32) {
+        let id = match self.rows.borrow().get(index as usize) { Some(Row::Group(id)) => *id, _ => return };
+        self.groups.toggle_collapsed(id);
+    }
+
+    fn rebuild(&self) {
+        let notebook = match self.notebook.upgrade() { Some(n) => n, None => return };
+        while let Some(child) = self.list.first_child() { self.list.remove(&child); }
+        let mut rows = Vec::new();
+        let mut selected = None;
+        let groups = self.groups.groups();
+        let mut last_group = None;
+
+        for i in 0..notebook.n_pages() {
+            let page = match notebook.nth_page(Some(i)) { Some(p) => p, None => continue };
+            let header = match tab_label::header(&page) { Some(h) => h, None => continue };
+
+            let group = header.group().and_then(|id| groups.iter().find(|g| g.id == id));
+            if let Some(g) = group {
+                if last_group != Some(g.id) {
+                    let label = Label::new(Some(&chip_name(&g.name, self.collapsed.get())));
+                    label.add_css_class("tab-group-chip");
+                    label.add_css_class(COLORS[g.color as usize % COLORS.len()].1);
+                    label.set_halign(Align::Start);
+                    let row = ListBoxRow::builder().child(&label).tooltip_text(if g.collapsed { "Expand Group" } else { "Collapse Group" }).build();
+                    self.list.append(&row);
+                    rows.push(Row::Group(g.id));
+                }
+                if g.collapsed { last_group = Some(g.id); continue; }
+            }
+            last_group = group.map(|g| g.id);
+
+            let row_box = Box::new(Orientation::Horizontal, 6);
+            let icon = favicons::tab_image();
+            if let Some(paintable) = header.favicon() { icon.set_from_paintable(Some(&paintable)); }
+            row_box.append(&icon);
+            if group.is_some() { row_box.set_margin_start(12); }
+
+            let title = row_title(&header.title(), &header.uri());
+            if !self.collapsed.get() {
+                let label = Label::new(Some(&title));
+                label.set_ellipsize(pango::EllipsizeMode::End);
+                label.set_hexpand(true);
+                label.set_xalign(0.0);
+                row_box.append(&label);
+                if !header.is_pinned() {
+                    let close_btn = Button::builder().icon_name("window-close-symbolic").build();
+                    close_btn.add_css_class("tab-close-btn");
+                    let tab_close = header.close_button().clone();
+                    close_btn.connect_clicked(move |_| tab_close.emit_clicked());
+                    row_box.append(&close_btn);
+                }
+            }
+
+            let row = ListBoxRow::builder().child(&row_box).tooltip_text(&title).build();
+            header.add_menu_to(&row);
+            self.list.append(&row);
+            if notebook.current_page() == Some(i) { selected = Some(row); }
+            rows.push(Row::Tab(page));
+        }
+
+        *self.rows.borrow_mut() = rows;
+        self.syncing.set(true);
+        self.list.select_row(selected.as_ref());
+        self.syncing.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chip_name, row_title};
+
+    #[test]
+    fn rows_fall_back_from_title_to_address() {
+        assert_eq!(row_title("Rust", "https://rust-lang.org/"), "Rust");
+        assert_eq!(row_title("", "https://rust-lang.org/"), "https://rust-lang.org/");
+        assert_eq!(row_title("", ""), "New Tab");
+    }
+
+    #[test]
+    fn collapsed_chips_keep_the_first_letter() {
+        assert_eq!(chip_name("Work", false), "Work");
+        assert_eq!(chip_name("Work", true), "W");
+        assert_eq!(chip_name("Élan", true), "É");
+        assert_eq!(chip_name("", true), "");
+    }
+}
@@ -0,0 +1,340 @@
+// --- COOKIES & SITE DATA ---
+use gtk::prelude::*;
+use gtk::{gio, glib, Align, Box, Button, Entry, Label, ListBox, ListBoxRow, Notebook, Orientation, PolicyType, ScrolledWindow, SearchEntry, SelectionMode, Separator, Window};
+use webkit6::prelude::*;
+use webkit6::{CookieAcceptPolicy, NetworkSession, UserContentFilter, UserContentFilterStore, WebsiteData, WebsiteDataTypes};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::clear_data::{categories, domain_matches, normalize_domain};
+use crate::tab_page;
+use crate::{is_amnesia_session, profile_path, save_config, AppConfig, CookieException};
+
+pub const COOKIE_POLICIES: [&str; 3] = ["Accept All Cookies", "Block Third-Party Cookies", "Block All Cookies"];
+const COOKIE_FILTER_ID: &str = "rusty-cookie-exceptions";
+
+pub type FilterSlot = Rc<RefCell<Option<UserContentFilter>>>;
+
+// Letters, digits and inner hyphens in dot-separated labels. Anything else
+// has no business in the content-rule regexes below.
+fn is_valid_host(domain: &str) -> bool {
+    domain.len() <= 253 && domain.split('.').all(|label| {
+        !label.is_empty() && label.len() <= 63 && !label.starts_with('-') && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+fn host_filter(domain: &str) -> String {
+    // Exceptions are checked when added; escaping covers older settings files.
+    let escaped: String = domain.chars().flat_map(|c| {
+        let special = "\\^$.|?*+()[]{}".contains(c);
+        special.then_some('\\').into_iter().chain(std::iter::once(c))
+    }).collect();
+    format!("^[a-z]+://([^/]*\\.)?{}[:/]", escaped)
+}
+
+// WebKit's cookie manager only has a session-wide policy. Per-domain
+// exceptions are expressed as content rules instead; "allow" exceptions under
+// a restrictive policy mean the policy itself has to be emulated with rules.
+// Rules only cover HTTP cookies, not document.cookie.
+pub fn cookie_rules(policy: u32, exceptions: &[CookieException]) -> (CookieAcceptPolicy, Vec<serde_json::Value>) {
+    let has_allow = exceptions.iter().any(|e| e.allow);
+    let mut rules = Vec::new();
+    let accept = match policy {
+        1 if !has_allow => CookieAcceptPolicy::NoThirdParty,
+        2 if !has_allow => CookieAcceptPolicy::Never,
+        1 => {
+            rules.push(serde_json::json!({ "trigger": { "url-filter": ".*", "load-type": ["third-party"] }, "action": { "type": "block-cookies" } }));
+            CookieAcceptPolicy::Always
+        }
+        2 => {
+            rules.push(serde_json::json!({ "trigger": { "url-filter": ".*" }, "action": { "type": "block-cookies" } }));
+            CookieAcceptPolicy::Always
+        }
+        _ => CookieAcceptPolicy::Always,
+    };
+    if !rules.is_empty() {
+        for e in exceptions.iter().filter(|e| e.allow) {
+            rules.push(serde_json::json!({ "trigger": { "url-filter": host_filter(&e.domain) }, "action": { "type": "ignore-previous-rules" } }));
+        }
+    }
+    for e in exceptions.iter().filter(|e| !e.allow) {
+        rules.push(serde_json::json!({ "trigger": { "url-filter": host_filter(&e.domain) }, "action": { "type": "block-cookies" } }));
+    }
+    (accept, rules)
+}
+
+// Exceptions need a compiled filter on disk, so amnesia sessions get the plain policy only.
+fn effective_exceptions(config: &AppConfig) -> &[CookieException] {
+    if is_amnesia_session() { &[] } else { &config.cookie_exceptions }
+}
+
+pub fn accept_policy(config: &AppConfig) -> CookieAcceptPolicy {
+    cookie_rules(config.cookie_policy, effective_exceptions(config)).0
+}
+
+// Every window's tabs share the one filter.
+pub fn apply_cookie_settings(config: &AppConfig, session: &NetworkSession, filter_slot: &FilterSlot, notebooks: &[Notebook]) {
+    let (accept, rules) = cookie_rules(config.cookie_policy, effective_exceptions(config));
+    session.cookie_manager().set_accept_policy(accept);
+
+    if rules.is_empty() {
+        install_filter(filter_slot, notebooks, None);
+        return;
+    }
+
+    let mut path = profile_path(glib::user_cache_dir());
+    path.push("content-filters");
+    let store = UserContentFilterStore::new(&path.to_string_lossy());
+    let source = glib::Bytes::from_owned(serde_json::to_vec(&rules).unwrap_or_default());
+    let slot = filter_slot.clone();
+    let notebooks_weak: Vec<_> = notebooks.iter().map(|nb| nb.downgrade()).collect();
+    store.save(COOKIE_FILTER_ID, &source, gio::Cancellable::NONE, move |result| {
+        if let Ok(filter) = result {
+            let notebooks: Vec<Notebook> = notebooks_weak.iter().filter_map(|nb| nb.upgrade()).collect();
+            install_filter(&slot, &notebooks, Some(filter));
+        }
+    });
+}
+
+fn install_filter(slot: &FilterSlot, notebooks: &[Notebook], filter: Option<UserContentFilter>) {
+    for notebook in notebooks {
+        for i in 0..notebook.n_pages() {
+            if let Some(webview) = notebook.nth_page(Some(i)).and_then(|p| tab_page::webview(&p)) {
+                if let Some(manager) = webview.user_content_manager() {
+                    if let Some(old) = slot.borrow().as_ref() { manager.remove_filter(old); }
+                    if let Some(new) = &filter { manager.add_filter(new); }
+                }
+            }
+        }
+    }
+    *slot.borrow_mut() = filter;
+}
+
+fn describe_types(types: WebsiteDataTypes) -> String {
+    let names: Vec<&str> = categories().into_iter().filter(|(_, kinds)| types.intersects(*kinds)).map(|(name, _)| name).collect();
+    if names.is_empty() { "Other data".to_string() } else { names.join(", ") }
+}
+
+struct SiteDataWindow {
+    session: NetworkSession,
+    state: Rc<RefCell<AppConfig>>,
+    on_change: Rc<dyn Fn()>,
+    search: SearchEntry,
+    site_list: ListBox,
+    site_rows: RefCell<Vec<(ListBoxRow, String)>>,
+    exception_list: ListBox,
+}
+
+impl SiteDataWindow {
+    fn refresh_sites(self: &Rc<Self>) {
+        while let Some(child) = self.site_list.first_child() { self.site_list.remove(&child); }
+        self.site_rows.borrow_mut().clear();
+
+        let this = Rc::downgrade(self);
+        self.session.website_data_manager().fetch(WebsiteDataTypes::ALL, gio::Cancellable::NONE, move |result| {
+            let this = match this.upgrade() { Some(t) => t, None => return };
+            let mut records = match result {
+                Ok(r) => r,
+                Err(e) => {
+                    this.site_list.append(&Label::new(Some(&format!("Could not load site data: {}", e))));
+                    return;
+                }
+            };
+            records.sort_by_key(|r| r.name().map(|n| n.to_string()).unwrap_or_default());
+            for record in records {
+                let row = this.site_row(record);
+                this.site_list.append(&row.0);
+                this.site_rows.borrow_mut().push(row);
+            }
+            this.apply_search();
+        });
+    }
+
+    fn site_row(self: &Rc<Self>, record: WebsiteData) -> (ListBoxRow, String) {
+        let name = record.name().map(|n| n.to_string()).unwrap_or_default();
+        let types = record.types();
+
+        let hbox = Box::new(Orientation::Horizontal, 10);
+        hbox.set_margin_top(6); hbox.set_margin_bottom(6); hbox.set_margin_start(6); hbox.set_margin_end(6);
+        let text_box = Box::new(Orientation::Vertical, 2);
+        text_box.set_hexpand(true);
+        let name_lbl = Label::new(None); name_lbl.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(&name))); name_lbl.set_halign(Align::Start);
+        let types_lbl = Label::new(Some(&describe_types(types))); types_lbl.set_halign(Align::Start); types_lbl.set_sensitive(false);
+        text_box.append(&name_lbl);
+        text_box.append(&types_lbl);
+        hbox.append(&text_box);
+
+        let size = record.size(types);
+        if size > 0 { hbox.append(&Label::new(Some(&glib::format_size(size)))); }
+
+        let delete_btn = Button::builder().icon_name("user-trash-symbolic").tooltip_text("Remove Site Data").build();
+        hbox.append(&delete_btn);
+
+        let this = Rc::downgrade(self);
+        delete_btn.connect_clicked(move |btn| {
+            let this = match this.upgrade() { Some(t) => t, None => return };
+            btn.set_sensitive(false);
+            let refresh = Rc::downgrade(&this);
+            this.session.website_data_manager().remove(WebsiteDataTypes::ALL, &[record.clone()], gio::Cancellable::NONE, move |_| {
+                if let Some(t) = refresh.upgrade() { t.refresh_sites(); }
+            });
+        });
+
+        let row = ListBoxRow::new();
+        row.set_child(Some(&hbox));
+        (row, name)
+    }
+
+    fn apply_search(&self) {
+        let query = normalize_domain(&self.search.text());
+        for (row, name) in self.site_rows.borrow().iter() {
+            row.set_visible(query.is_empty() || name.contains(&query) || domain_matches(name, &query));
+        }
+    }
+
+    fn refresh_exceptions(self: &Rc<Self>) {
+        while let Some(child) = self.exception_list.first_child() { self.exception_list.remove(&child); }
+        let exceptions = self.state.borrow().cookie_exceptions.clone();
+        for (idx, exception) in exceptions.iter().enumerate() {
+            let hbox = Box::new(Orientation::Horizontal, 10);
+            hbox.set_margin_start(6); hbox.set_margin_end(6);
+            let domain_lbl = Label::new(Some(&exception.domain)); domain_lbl.set_halign(Align::Start); domain_lbl.set_hexpand(true);
+            let kind_lbl = Label::new(Some(if exception.allow { "Allow" } else { "Block" }));
+            let remove_btn = Button::builder().icon_name("list-remove-symbolic").tooltip_text("Remove Exception").build();
+            hbox.append(&domain_lbl);
+            hbox.append(&kind_lbl);
+            hbox.append(&remove_btn);
+            self.exception_list.append(&hbox);
+
+            let this = Rc::downgrade(self);
+            remove_btn.connect_clicked(move |_| {
+                if let Some(this) = this.upgrade() {
+                    this.state.borrow_mut().cookie_exceptions.remove(idx);
+                    this.exceptions_changed();
+                }
+            });
+        }
+    }
+
+    // False when the input isn't a host name.
+    fn add_exception(self: &Rc<Self>, input: &str, allow: bool) -> bool {
+        let domain = normalize_domain(input);
+        if !is_valid_host(&domain) { return false; }
+        {
+            let mut state = self.state.borrow_mut();
+            state.cookie_exceptions.retain(|e| e.domain != domain);
+            state.cookie_exceptions.push(CookieException { domain, allow });
+        }
+        self.exceptions_changed();
+        true
+    }
+
+    fn exceptions_changed(self: &Rc<Self>) {
+        save_config(&self.state.borrow());
+        (self.on_change)();
+        self.refresh_exceptions();
+    }
+}
+
+pub fn show_site_data_window(parent: &impl IsA<Window>, session: &NetworkSession, state: Rc<RefCell<AppConfig>>, on_change: Rc<dyn Fn()>) {
+    let window = Window::builder().transient_for(parent).title("Cookies and Site Data").default_width(560).default_height(600).build();
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_top(20); vbox.set_margin_bottom(20); vbox.set_margin_start(20); vbox.set_margin_end(20);
+
+    let label_sites = Label::new(None); label_sites.set_markup("<b>Stored Website Data</b>"); label_sites.set_halign(Align::Start); vbox.append(&label_sites);
+    let search = SearchEntry::new();
+    search.set_placeholder_text(Some("Search sites"));
+    vbox.append(&search);
+
+    let site_list = ListBox::new();
+    site_list.set_selection_mode(SelectionMode::None);
+    site_list.set_placeholder(Some(&Label::new(Some("No sites have stored data."))));
+    let scroll = ScrolledWindow::builder().hscrollbar_policy(PolicyType::Never).vexpand(true).min_content_height(250).child(&site_list).build();
+    vbox.append(&scroll);
+
+    vbox.append(&Separator::new(Orientation::Horizontal));
+    let label_exc = Label::new(None); label_exc.set_markup("<b>Cookie Exceptions</b>"); label_exc.set_halign(Align::Start); vbox.append(&label_exc);
+    if is_amnesia_session() {
+        let amnesia_lbl = Label::new(None); amnesia_lbl.set_markup("<i>(Exceptions are not applied during an Amnesia session)</i>"); amnesia_lbl.set_halign(Align::Start); amnesia_lbl.set_sensitive(false); vbox.append(&amnesia_lbl);
+    }
+    let exc_box = Box::new(Orientation::Horizontal, 6);
+    let exc_entry = Entry::new(); exc_entry.set_hexpand(true); exc_entry.set_placeholder_text(Some("example.com"));
+    let allow_btn = Button::builder().label("Allow").build(); allow_btn.add_css_class("flat-button");
+    let block_btn = Button::builder().label("Block").build(); block_btn.add_css_class("flat-button");
+    exc_box.append(&exc_entry); exc_box.append(&allow_btn); exc_box.append(&block_btn);
+    vbox.append(&exc_box);
+
+    let exception_list = ListBox::new();
+    exception_list.set_selection_mode(SelectionMode::None);
+    exception_list.set_placeholder(Some(&Label::new(Some("No exceptions."))));
+    vbox.append(&exception_list);
+
+    window.set_child(Some(&vbox));
+
+    let this = Rc::new(SiteDataWindow {
+        session: session.clone(),
+        state,
+        on_change,
+        search: search.clone(),
+        site_list,
+        site_rows: RefCell::new(Vec::new()),
+        exception_list,
+    });
+
+    let this_search = Rc::downgrade(&this);
+    search.connect_search_changed(move |_| { if let Some(t) = this_search.upgrade() { t.apply_search(); } });
+
+    for (btn, allow) in [(allow_btn, true), (block_btn, false)] {
+        let this_exc = this.clone();
+        let entry = exc_entry.clone();
+        btn.connect_clicked(move |_| {
+            if this_exc.add_exception(&entry.text(), allow) { entry.set_text(""); } else { entry.add_css_class("error"); }
+        });
+    }
+    exc_entry.connect_changed(|entry| entry.remove_css_class("error"));
+
+    this.refresh_sites();
+    this.refresh_exceptions();
+    window.present();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cookie_rules, host_filter, is_valid_host};
+    use crate::CookieException;
+    use webkit6::CookieAcceptPolicy;
+
+    #[test]
+    fn host_names_are_valid() {
+        assert!(is_valid_host("example.com"));
+        assert!(is_valid_host("my-site.example.co.uk"));
+        assert!(is_valid_host("localhost"));
+    }
+
+    #[test]
+    fn regex_syntax_and_empty_labels_are_not() {
+        for bad in ["", "foo(bar", "a*.com", "[x].com", "a+b.com", "a?.com", "a|b.com", "example..com", ".example.com", "-a.com", "a b.com"] {
+            assert!(!is_valid_host(bad), "{:?} was accepted", bad);
+        }
+    }
+
+    #[test]
+    fn host_filter_escapes_everything() {
+        assert_eq!(host_filter("example.com"), "^[a-z]+://([^/]*\\.)?example\\.com[:/]");
+        assert_eq!(host_filter("foo(bar"), "^[a-z]+://([^/]*\\.)?foo\\(bar[:/]");
+    }
+
+    #[test]
+    fn allow_exceptions_turn_the_policy_into_rules() {
+        let exceptions = vec![CookieException { domain: "example.com".to_string(), allow: true }];
+        let (accept, rules) = cookie_rules(2, &exceptions);
+        assert_eq!(accept, CookieAcceptPolicy::Always);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1]["action"]["type"], "ignore-previous-rules");
+
+        let (accept, rules) = cookie_rules(2, &[]);
+        assert_eq!(accept, CookieAcceptPolicy::Never);
+        assert!(rules.is_empty());
+    }
+}
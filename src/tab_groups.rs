@@ -0,0 +1,324 @@
+// --- TAB GROUPS ---
+use gtk::prelude::*;
+use gtk::{gio, glib, Dialog, Entry, Notebook, ResponseType, Widget, Window};
+use serde::{Serialize, Deserialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::tab_label;
+use crate::window::BrowserWindow;
+
+// (name, css class) — the class colours the chip and the tab's underline.
+pub const COLORS: [(&str, &str); 6] = [
+    ("Blue", "group-blue"),
+    ("Green", "group-green"),
+    ("Orange", "group-orange"),
+    ("Purple", "group-purple"),
+    ("Red", "group-red"),
+    ("Grey", "group-grey"),
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TabGroup {
+    pub id: u32,
+    pub name: String,
+    pub color: u32,
+    pub collapsed: bool,
+}
+
+// Groups live per window; membership is stored on each tab's label.
+// A collapsed group keeps only its first tab (and the chip) in the tab bar.
+pub struct TabGroups {
+    notebook: glib::WeakRef<Notebook>,
+    groups: RefCell<Vec<TabGroup>>,
+}
+
+fn pages(notebook: &Notebook) -> Vec<Widget> {
+    (0..notebook.n_pages()).filter_map(|i| notebook.nth_page(Some(i))).collect()
+}
+
+fn group_of(page: &Widget) -> Option<u32> {
+    tab_label::header(page).and_then(|h| h.group())
+}
+
+// Numbered after the highest id so far, with the next colour in turn.
+fn next_group(groups: &[TabGroup]) -> TabGroup {
+    let id = groups.iter().map(|g| g.id + 1).max().unwrap_or(1);
+    TabGroup { id, name: format!("Group {}", id), color: groups.len() as u32 % COLORS.len() as u32, collapsed: false }
+}
+
+// Only the group's first tab carries the chip; collapsed, it counts the rest.
+fn chip_text(group: &TabGroup, first: bool, members: usize) -> Option<String> {
+    if !first { None }
+    else if group.collapsed { Some(format!("{} ({})", group.name, members)) }
+    else { Some(group.name.clone()) }
+}
+
+// Where a joining tab goes: right after the group's last member.
+fn slot_after(current: u32, last: u32) -> u32 {
+    if current > last { last + 1 } else { last }
+}
+
+impl TabGroups {
+    pub fn new(notebook: &Notebook) -> Rc<Self> {
+        let this = Rc::new(Self { notebook: notebook.downgrade(), groups: RefCell::new(Vec::new()) });
+        let weak = Rc::downgrade(&this);
+        notebook.connect_page_removed(move |_, _, _| {
+            if let Some(this) = weak.upgrade() { this.refresh(); }
+        });
+        let weak = Rc::downgrade(&this);
+        notebook.connect_page_reordered(move |_, _, _| {
+            if let Some(this) = weak.upgrade() { this.refresh(); }
+        });
+        this
+    }
+
+    pub fn groups(&self) -> Vec<TabGroup> {
+        self.groups.borrow().clone()
+    }
+
+    // From a saved session; tabs pick their group back up via set_group.
+    pub fn restore(&self, groups: Vec<TabGroup>) {
+        *self.groups.borrow_mut() = groups;
+    }
+
+    pub fn members(&self, id: u32) -> Vec<Widget> {
+        self.notebook.upgrade().map(|nb| pages(&nb)).unwrap_or_default()
+            .into_iter()
+            .filter(|p| group_of(p) == Some(id))
+            .collect()
+    }
+
+    fn with_group(&self, id: u32, f: impl FnOnce(&mut TabGroup)) {
+        if let Some(group) = self.groups.borrow_mut().iter_mut().find(|g| g.id == id) { f(group); }
+        self.refresh();
+    }
+
+    // Ctrl+clicked tabs, or just the one the menu was opened on.
+    pub fn create(&self, page: &Widget) -> u32 {
+        let notebook = match self.notebook.upgrade() { Some(n) => n, None => return 0 };
+        let mut tabs: Vec<Widget> = pages(&notebook).into_iter()
+            .filter(|p| tab_label::header(p).map_or(false, |h| h.is_selected() && !h.is_pinned()))
+            .collect();
+        if !tabs.contains(page) { tabs.push(page.clone()); }
+
+        let group = next_group(&self.groups.borrow());
+        let id = group.id;
+        self.groups.borrow_mut().push(group);
+        for p in &tabs {
+            if let Some(h) = tab_label::header(p) { h.set_selected(false); }
+        }
+        for p in &tabs { self.add(p, id); }
+        id
+    }
+
+    // Members are kept next to each other, in the order they joined.
+    pub fn add(&self, page: &Widget, id: u32) {
+        let (notebook, header) = match (self.notebook.upgrade(), tab_label::header(page)) { (Some(n), Some(h)) => (n, h), _ => return };
+        let last = self.members(id).into_iter().filter(|p| p != page).filter_map(|p| notebook.page_num(&p)).max();
+        header.set_group(Some(id));
+        if let (Some(last), Some(current)) = (last, notebook.page_num(page)) {
+            notebook.reorder_child(page, Some(slot_after(current, last)));
+        }
+        self.refresh();
+    }
+
+    pub fn remove(&self, page: &Widget) {
+        page.set_visible(true);
+        if let Some(h) = tab_label::header(page) { h.set_group(None); }
+        self.refresh();
+    }
+
+    pub fn rename(&self, id: u32, name: &str) {
+        self.with_group(id, |g| g.name = name.to_string());
+    }
+
+    pub fn set_color(&self, id: u32, color: u32) {
+        self.with_group(id, |g| g.color = color);
+    }
+
+    pub fn toggle_collapsed(&self, id: u32) {
+        self.with_group(id, |g| g.collapsed = !g.collapsed);
+    }
+
+    pub fn ungroup(&self, id: u32) {
+        for page in self.members(id) { self.remove(&page); }
+        self.groups.borrow_mut().retain(|g| g.id != id);
+    }
+
+    pub fn close(&self, id: u32) {
+        let notebook = match self.notebook.upgrade() { Some(n) => n, None => return };
+        for page in self.members(id) {
+            if let Some(idx) = notebook.page_num(&page) { notebook.remove_page(Some(idx)); }
+        }
+    }
+
+    // Redraws chips, colours and collapsed state; drops groups with no tabs left.
+    pub fn refresh(&self) {
+        let notebook = match self.notebook.upgrade() { Some(n) => n, None => return };
+        let tabs = pages(&notebook);
+        self.groups.borrow_mut().retain(|g| tabs.iter().any(|p| group_of(p) == Some(g.id)));
+
+        let groups = self.groups.borrow().clone();
+        let mut seen: Vec<u32> = Vec::new();
+        for page in &tabs {
+            let header = match tab_label::header(page) { Some(h) => h, None => continue };
+            let group = match header.group().and_then(|id| groups.iter().find(|g| g.id == id)) {
+                Some(g) => g,
+                None => {
+                    header.decorate(None, None);
+                    page.set_visible(true);
+                    continue;
+                }
+            };
+            let first = !seen.contains(&group.id);
+            seen.push(group.id);
+            let color = COLORS[group.color as usize % COLORS.len()].1;
+            let members = tabs.iter().filter(|t| group_of(t) == Some(group.id)).count();
+            let chip = chip_text(group, first, members);
+            header.decorate(Some(color), chip);
+            page.set_visible(first || !group.collapsed);
+        }
+
+        // Never leave the selection on a tab that just got tucked away.
+        if let Some(current) = notebook.nth_page(notebook.current_page()) {
+            if !current.is_visible() {
+                if let Some(first) = group_of(&current).and_then(|id| self.members(id).into_iter().next()) {
+                    notebook.set_current_page(notebook.page_num(&first));
+                }
+            }
+        }
+    }
+
+    pub fn menu_section(&self, page: &Widget) -> gio::Menu {
+        let menu = gio::Menu::new();
+        let header = match tab_label::header(page) { Some(h) => h, None => return menu };
+        if header.is_pinned() { return menu; }
+
+        menu.append(Some("Add Tab to New Group"), Some("tab.new-group"));
+        let others: Vec<TabGroup> = self.groups().into_iter().filter(|g| Some(g.id) != header.group()).collect();
+        if !others.is_empty() {
+            let submenu = gio::Menu::new();
+            for g in others {
+                submenu.append(Some(&g.name), Some(&format!("tab.add-to-group(uint32 {})", g.id)));
+            }
+            menu.append_submenu(Some("Add Tab to Group"), &submenu);
+        }
+
+        if let Some(group) = header.group().and_then(|id| self.groups().into_iter().find(|g| g.id == id)) {
+            menu.append(Some("Remove Tab from Group"), Some("tab.leave-group"));
+            let group_menu = gio::Menu::new();
+            group_menu.append(Some("Rename Group…"), Some("tab.rename-group"));
+            let colors = gio::Menu::new();
+            for (i, (name, _)) in COLORS.iter().enumerate() {
+                colors.append(Some(name), Some(&format!("tab.group-color(uint32 {})", i)));
+            }
+            group_menu.append_submenu(Some("Group Color"), &colors);
+            group_menu.append(Some(if group.collapsed { "Expand Group" } else { "Collapse Group" }), Some("tab.collapse-group"));
+            group_menu.append(Some("Move Group to New Window"), Some("tab.move-group-window"));
+            group_menu.append(Some("Ungroup"), Some("tab.ungroup"));
+            group_menu.append(Some("Close Group"), Some("tab.close-group"));
+            menu.append_section(Some(&group.name), &group_menu);
+        }
+        menu
+    }
+
+    // Tab-scoped actions behind menu_section and the group chip. Tabs can move
+    // between windows, so the groups are looked up when an action runs.
+    pub fn install_actions(actions: &gio::SimpleActionGroup, page: &Widget, on_change: Rc<dyn Fn()>) {
+        let add = |name: &str, param: Option<&glib::VariantTy>, f: Box<dyn Fn(&Rc<Self>, &Widget, Option<&glib::Variant>)>| {
+            let action = gio::SimpleAction::new(name, param);
+            let page = page.downgrade();
+            let on_change = on_change.clone();
+            action.connect_activate(move |_, value| {
+                let page = match page.upgrade() { Some(p) => p, None => return };
+                if let Some(window) = BrowserWindow::of(&page) {
+                    f(window.groups(), &page, value);
+                    on_change();
+                }
+            });
+            actions.add_action(&action);
+        };
+        let group = |page: &Widget| group_of(page);
+
+        add("new-group", None, Box::new(|this, page, _| { this.create(page); }));
+        add("add-to-group", Some(glib::VariantTy::UINT32), Box::new(|this, page, v| {
+            if let Some(id) = v.and_then(|v| v.get::<u32>()) { this.add(page, id); }
+        }));
+        add("leave-group", None, Box::new(|this, page, _| this.remove(page)));
+        let on_rename = on_change.clone();
+        add("rename-group", None, Box::new(move |this, page, _| {
+            if let Some(id) = group(page) { this.show_rename_dialog(page, id, on_rename.clone()); }
+        }));
+        add("group-color", Some(glib::VariantTy::UINT32), Box::new(move |this, page, v| {
+            if let (Some(id), Some(color)) = (group(page), v.and_then(|v| v.get::<u32>())) { this.set_color(id, color); }
+        }));
+        add("collapse-group", None, Box::new(move |this, page, _| {
+            if let Some(id) = group(page) { this.toggle_collapsed(id); }
+        }));
+        add("ungroup", None, Box::new(move |this, page, _| {
+            if let Some(id) = group(page) { this.ungroup(id); }
+        }));
+        add("close-group", None, Box::new(move |this, page, _| {
+            if let Some(id) = group(page) { this.close(id); }
+        }));
+    }
+
+    fn show_rename_dialog(self: &Rc<Self>, page: &Widget, id: u32, on_change: Rc<dyn Fn()>) {
+        let parent = page.root().and_then(|r| r.downcast::<Window>().ok());
+        let dialog = Dialog::builder().modal(true).title("Rename Group").build();
+        dialog.set_transient_for(parent.as_ref());
+        dialog.add_button("Cancel", ResponseType::Cancel);
+        dialog.add_button("Rename", ResponseType::Accept);
+        dialog.set_default_response(ResponseType::Accept);
+
+        let entry = Entry::new();
+        entry.set_activates_default(true);
+        entry.set_margin_top(12); entry.set_margin_bottom(12); entry.set_margin_start(12); entry.set_margin_end(12);
+        if let Some(g) = self.groups().into_iter().find(|g| g.id == id) { entry.set_text(&g.name); }
+        dialog.content_area().append(&entry);
+
+        let this = Rc::downgrade(self);
+        dialog.connect_response(move |d, response| {
+            let name = entry.text().trim().to_string();
+            if let (ResponseType::Accept, Some(this), false) = (response, this.upgrade(), name.is_empty()) {
+                this.rename(id, &name);
+                on_change();
+            }
+            d.close();
+        });
+        dialog.show();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chip_text, next_group, slot_after, TabGroup, COLORS};
+
+    fn group(id: u32, collapsed: bool) -> TabGroup {
+        TabGroup { id, name: format!("Group {}", id), color: 0, collapsed }
+    }
+
+    #[test]
+    fn new_groups_get_fresh_ids_and_cycle_colours() {
+        let first = next_group(&[]);
+        assert_eq!((first.id, first.name.as_str(), first.color), (1, "Group 1", 0));
+        let second = next_group(&[group(1, false), group(4, false)]);
+        assert_eq!((second.id, second.color), (5, 2));
+        let many: Vec<TabGroup> = (1..=COLORS.len() as u32).map(|id| group(id, false)).collect();
+        assert_eq!(next_group(&many).color, 0);
+    }
+
+    #[test]
+    fn only_the_first_tab_shows_the_chip() {
+        assert_eq!(chip_text(&group(1, false), true, 3).as_deref(), Some("Group 1"));
+        assert_eq!(chip_text(&group(1, true), true, 3).as_deref(), Some("Group 1 (3)"));
+        assert_eq!(chip_text(&group(1, true), false, 3), None);
+    }
+
+    #[test]
+    fn joining_tabs_land_after_the_last_member() {
+        assert_eq!(slot_after(5, 2), 3);
+        assert_eq!(slot_after(0, 2), 2);
+    }
+}
@@ -0,0 +1,392 @@
+// --- TAB LABELS ---
+use gtk::prelude::*;
+use gtk::{gdk, glib, gio, pango, Align, Box, Button, GestureClick, Image, Label, Notebook, Orientation, PopoverMenu, Spinner, Widget};
+use webkit6::prelude::*;
+use webkit6::{LoadEvent, NetworkSession, WebView};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::crashes;
+use crate::favicons;
+use crate::permissions;
+use crate::tab_groups;
+use crate::tab_page;
+
+pub struct TabLabel {
+    root: Box,
+    group_chip: Button,
+    spinner: Spinner,
+    icon: Image,
+    crashed_icon: Image,
+    label: Label,
+    audio_btn: Button,
+    close_btn: Button,
+    incognito: bool,
+    pinned: Cell<bool>,
+    crashed: Cell<bool>,
+    group: Cell<Option<u32>>,
+    selected: Cell<bool>,
+    menu: RefCell<Option<(gio::SimpleActionGroup, Rc<dyn Fn() -> gio::Menu>)>>,
+    // Kept here rather than read off the webview, which a tab doesn't always have.
+    uri: RefCell<String>,
+    title: RefCell<String>,
+    last_active: Cell<Instant>,
+    on_change: RefCell<Vec<std::boxed::Box<dyn Fn()>>>,
+}
+
+const DATA_KEY: &str = "rusty-tab-label";
+
+// The label attached to a tab's page or webview, if it has one.
+pub fn header(webview: &impl IsA<Widget>) -> Option<Rc<TabLabel>> {
+    unsafe { webview.as_ref().data::<Rc<TabLabel>>(DATA_KEY).map(|p| p.as_ref().clone()) }
+}
+
+pub fn pinned_before(notebook: &Notebook, webview: &impl IsA<Widget>) -> u32 {
+    (0..notebook.n_pages())
+        .filter_map(|i| notebook.nth_page(Some(i)))
+        .filter(|page| page != webview.as_ref())
+        .filter(|page| header(page).map_or(false, |h| h.is_pinned()))
+        .count() as u32
+}
+
+// Pinned tabs sit together at the left and can't be dragged, not even to another window.
+pub fn set_pinned(notebook: &Notebook, page: &impl IsA<Widget>, pinned: bool) {
+    let header = match header(page) { Some(h) => h, None => return };
+    header.pinned.set(pinned);
+    match tab_page::webview(page) {
+        Some(webview) => header.refresh(&webview),
+        None => header.show_placeholder(&header.uri(), &header.title()),
+    }
+    notebook.set_tab_reorderable(page, !pinned);
+    notebook.set_tab_detachable(page, !pinned);
+    notebook.reorder_child(page, Some(pinned_before(notebook, page)));
+}
+
+// Dragging a normal tab into the pinned strip puts it back just after it.
+pub fn keep_pinned_first(notebook: &Notebook, page: &Widget, position: u32) {
+    if header(page).map_or(true, |h| h.is_pinned()) { return; }
+    let allowed = after_pinned(position, pinned_before(notebook, page));
+    if allowed != position { notebook.reorder_child(page, Some(allowed)); }
+}
+
+fn after_pinned(position: u32, pinned: u32) -> u32 {
+    position.max(pinned)
+}
+
+// A pinned tab keeps to its site; a link anywhere else opens beside it.
+pub fn leaves_pinned_site(current_uri: Option<&str>, target: &str) -> bool {
+    let current = current_uri.and_then(permissions::origin_of);
+    current.is_none() || permissions::origin_of(target) != current
+}
+
+impl TabLabel {
+    pub fn new(incognito: bool) -> Rc<Self> {
+        let root = Box::new(Orientation::Horizontal, 0);
+        root.set_valign(Align::Center);
+
+        let group_chip = Button::builder().action_name("tab.collapse-group").tooltip_text("Collapse or Expand Group").build();
+        group_chip.add_css_class("tab-group-chip");
+        group_chip.set_visible(false);
+
+        let spinner = Spinner::new();
+        spinner.set_size_request(16, 16);
+        spinner.set_margin_end(6);
+        spinner.set_visible(false);
+        let icon = favicons::tab_image();
+        let crashed_icon = Image::from_icon_name("dialog-error-symbolic");
+        crashed_icon.set_margin_end(6);
+        crashed_icon.add_css_class("tab-crashed");
+        crashed_icon.set_visible(false);
+
+        // Width-based ellipsizing; byte slicing panicked on multi-byte titles.
+        let label = Label::new(Some(if incognito { "🕵️ Loading..." } else { "Loading..." }));
+        label.set_ellipsize(pango::EllipsizeMode::End);
+        label.set_max_width_chars(18);
+        label.set_width_chars(6);
+        label.set_xalign(0.0);
+
+        let audio_btn = Button::builder().icon_name("audio-volume-high-symbolic").action_name("tab.toggle-mute").tooltip_text("Mute Tab").build();
+        audio_btn.add_css_class("tab-audio-btn");
+        audio_btn.set_visible(false);
+        let close_btn = Button::builder().icon_name("window-close-symbolic").action_name("tab.close").build();
+        close_btn.add_css_class("tab-close-btn");
+
+        root.append(&group_chip);
+        root.append(&spinner);
+        root.append(&icon);
+        root.append(&crashed_icon);
+        root.append(&label);
+        root.append(&audio_btn);
+        root.append(&close_btn);
+        root.show();
+
+        Rc::new(Self { root, group_chip, spinner, icon, crashed_icon, label, audio_btn, close_btn, incognito, pinned: Cell::new(false), crashed: Cell::new(false), group: Cell::new(None), selected: Cell::new(false), menu: RefCell::new(None),
+            uri: RefCell::new(String::new()), title: RefCell::new(String::new()), last_active: Cell::new(Instant::now()), on_change: RefCell::new(Vec::new()) })
+    }
+
+    pub fn widget(&self) -> &Box {
+        &self.root
+    }
+
+    pub fn close_button(&self) -> &Button {
+        &self.close_btn
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned.get()
+    }
+
+    pub fn is_incognito(&self) -> bool {
+        self.incognito
+    }
+
+    pub fn group(&self) -> Option<u32> {
+        self.group.get()
+    }
+
+    pub fn set_group(&self, group: Option<u32>) {
+        self.group.set(group);
+    }
+
+    pub fn uri(&self) -> String {
+        self.uri.borrow().clone()
+    }
+
+    pub fn title(&self) -> String {
+        self.title.borrow().clone()
+    }
+
+    pub fn favicon(&self) -> Option<gdk::Paintable> {
+        self.icon.paintable()
+    }
+
+    // When the tab was last selected (or left); hibernation goes by this.
+    pub fn touch(&self) {
+        self.last_active.set(Instant::now());
+    }
+
+    pub fn last_active(&self) -> Instant {
+        self.last_active.get()
+    }
+
+    // Called after every title, address or icon change, e.g. by the sidebar.
+    pub fn connect_changed(&self, f: impl Fn() + 'static) {
+        self.on_change.borrow_mut().push(std::boxed::Box::new(f));
+    }
+
+    fn changed(&self) {
+        for f in self.on_change.borrow().iter() { f(); }
+    }
+
+    pub fn is_selected(&self) -> bool {
+        self.selected.get()
+    }
+
+    pub fn set_selected(&self, selected: bool) {
+        self.selected.set(selected);
+        if selected { self.root.add_css_class("tab-selected"); } else { self.root.remove_css_class("tab-selected"); }
+    }
+
+    // Group colour underline, plus the name chip on the group's first tab.
+    pub fn decorate(&self, color: Option<&str>, chip: Option<String>) {
+        for (_, class) in tab_groups::COLORS.iter() {
+            self.root.remove_css_class(class);
+            self.group_chip.remove_css_class(class);
+        }
+        if let Some(class) = color {
+            self.root.add_css_class(class);
+            self.group_chip.add_css_class(class);
+        }
+        self.group_chip.set_visible(chip.is_some());
+        self.group_chip.set_label(&chip.unwrap_or_default());
+    }
+
+    // Right-click menu; the model is rebuilt on every open so labels can follow state.
+    pub fn set_menu(&self, actions: &gio::SimpleActionGroup, build: impl Fn() -> gio::Menu + 'static) {
+        *self.menu.borrow_mut() = Some((actions.clone(), Rc::new(build)));
+        self.add_menu_to(&self.root);
+    }
+
+    // Runs one of the tab's own actions, e.g. from a window shortcut.
+    pub fn activate(&self, name: &str) {
+        let actions = self.menu.borrow().as_ref().map(|(a, _)| a.clone());
+        if let Some(actions) = actions { actions.activate_action(name, None); }
+    }
+
+    // Any other widget standing in for this tab (e.g. a sidebar row) gets the same menu.
+    pub fn add_menu_to(&self, widget: &impl IsA<Widget>) {
+        let (actions, build) = match self.menu.borrow().clone() { Some(m) => m, None => return };
+        widget.insert_action_group("tab", Some(&actions));
+        let click = GestureClick::new();
+        click.set_button(gdk::BUTTON_SECONDARY);
+        let target = widget.as_ref().downgrade();
+        click.connect_pressed(move |_, _, x, y| {
+            let target = match target.upgrade() { Some(t) => t, None => return };
+            let popover = PopoverMenu::from_model(Some(&build()));
+            popover.set_parent(&target);
+            popover.set_has_arrow(false);
+            popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+            popover.connect_closed(|p| {
+                let p = p.clone();
+                glib::idle_add_local_once(move || p.unparent());
+            });
+            popover.popup();
+        });
+        widget.add_controller(click);
+    }
+
+    // Once per tab, on the notebook page that outlives its webviews.
+    pub fn attach_page(self: &Rc<Self>, page: &impl IsA<Widget>) {
+        unsafe { page.as_ref().set_data(DATA_KEY, self.clone()); }
+
+        // Ctrl+click builds a selection for "Add Tab to New Group".
+        let select = GestureClick::new();
+        let this = Rc::downgrade(self);
+        select.connect_pressed(move |gesture, _, _, _| {
+            if !gesture.current_event_state().contains(gdk::ModifierType::CONTROL_MASK) { return; }
+            if let Some(this) = this.upgrade() {
+                if !this.pinned.get() { this.set_selected(!this.selected.get()); }
+            }
+        });
+        self.root.add_controller(select);
+    }
+
+    // Once per webview the tab gets.
+    pub fn attach(self: &Rc<Self>, webview: &WebView) {
+        unsafe { webview.set_data(DATA_KEY, self.clone()); }
+
+        // The webview's handlers own the label; it holds no reference back.
+        let this = self.clone();
+        let refresh = move |wv: &WebView| this.refresh(wv);
+        let r = refresh.clone();
+        webview.connect_title_notify(move |wv| r(wv));
+        let r = refresh.clone();
+        webview.connect_uri_notify(move |wv| r(wv));
+        let r = refresh.clone();
+        webview.connect_favicon_notify(move |wv| r(wv));
+        let r = refresh.clone();
+        webview.connect_is_loading_notify(move |wv| r(wv));
+        let r = refresh.clone();
+        webview.connect_is_playing_audio_notify(move |wv| r(wv));
+        let r = refresh.clone();
+        webview.connect_is_muted_notify(move |wv| r(wv));
+
+        let this = self.clone();
+        webview.connect_web_process_terminated(move |wv, reason| {
+            this.crashed.set(true);
+            this.crashed_icon.set_tooltip_text(Some(crashes::reason_label(reason)));
+            this.refresh(wv);
+        });
+
+        let this = self.clone();
+        webview.connect_load_changed(move |wv, event| {
+            if event == LoadEvent::Started && this.crashed.replace(false) { this.refresh(wv); }
+        });
+    }
+
+    fn refresh(&self, webview: &WebView) {
+        let uri = webview.uri().map(|u| u.to_string()).unwrap_or_default();
+        let title = webview.title().map(|t| t.to_string()).filter(|t| !t.is_empty());
+        *self.uri.borrow_mut() = uri.clone();
+        *self.title.borrow_mut() = title.clone().unwrap_or_default();
+        self.show_text(&uri, title);
+
+        let crashed = self.crashed.get();
+        let loading = webview.is_loading() && !crashed;
+        self.crashed_icon.set_visible(crashed);
+        self.spinner.set_visible(loading);
+        self.spinner.set_spinning(loading);
+        self.icon.set_visible(!loading && !crashed);
+        favicons::update_image(&self.icon, webview);
+
+        let muted = webview.is_muted();
+        self.audio_btn.set_visible(webview.is_playing_audio() || muted);
+        self.audio_btn.set_icon_name(if muted { "audio-volume-muted-symbolic" } else { "audio-volume-high-symbolic" });
+        self.audio_btn.set_tooltip_text(Some(if muted { "Unmute Tab" } else { "Mute Tab" }));
+        self.changed();
+    }
+
+    // A tab that never had a webview this run takes its icon from the database.
+    pub fn load_favicon(&self, session: &NetworkSession) {
+        favicons::update_image_for_uri(&self.icon, session, &self.uri.borrow());
+        self.changed();
+    }
+
+    // A tab without a webview keeps its last title and favicon.
+    pub fn show_placeholder(&self, uri: &str, title: &str) {
+        *self.uri.borrow_mut() = uri.to_string();
+        *self.title.borrow_mut() = title.to_string();
+        self.show_text(uri, Some(title.to_string()).filter(|t| !t.is_empty()));
+        self.spinner.set_visible(false);
+        self.spinner.set_spinning(false);
+        self.icon.set_visible(!self.crashed.get());
+        self.audio_btn.set_visible(false);
+        self.changed();
+    }
+
+    fn show_text(&self, uri: &str, title: Option<String>) {
+        let pinned = self.pinned.get();
+        self.label.set_visible(!pinned);
+        self.close_btn.set_visible(!pinned);
+        self.label.set_text(&label_text(uri, title.as_deref(), self.incognito));
+        self.root.set_tooltip_text(Some(&tooltip_text(uri, title.as_deref())));
+    }
+}
+
+// The whole title; the label ellipsizes by width, never by bytes.
+fn label_text(uri: &str, title: Option<&str>, incognito: bool) -> String {
+    let text = title.map(str::to_string).unwrap_or_else(|| if uri.is_empty() { "New Tab".to_string() } else { uri.to_string() });
+    if incognito { format!("🕵️ {}", text) } else { text }
+}
+
+fn tooltip_text(uri: &str, title: Option<&str>) -> String {
+    match title {
+        Some(t) if !uri.is_empty() => format!("{}\n{}", t, uri),
+        Some(t) => t.to_string(),
+        None => uri.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{after_pinned, label_text, leaves_pinned_site, tooltip_text};
+
+    #[test]
+    fn label_prefers_the_title_then_the_address() {
+        assert_eq!(label_text("https://example.com/", Some("Example"), false), "Example");
+        assert_eq!(label_text("https://example.com/", None, false), "https://example.com/");
+        assert_eq!(label_text("", None, false), "New Tab");
+        assert_eq!(label_text("", None, true), "🕵️ New Tab");
+    }
+
+    #[test]
+    fn multi_byte_titles_are_kept_whole() {
+        let title = "日本語のページタイトルはとても長いことがあります — ✓";
+        assert_eq!(label_text("https://example.jp/", Some(title), false), title);
+    }
+
+    #[test]
+    fn tooltip_shows_title_and_address() {
+        assert_eq!(tooltip_text("https://example.com/", Some("Example")), "Example\nhttps://example.com/");
+        assert_eq!(tooltip_text("", Some("Example")), "Example");
+        assert_eq!(tooltip_text("https://example.com/", None), "https://example.com/");
+    }
+
+    #[test]
+    fn unpinned_tabs_stay_after_the_pinned_ones() {
+        assert_eq!(after_pinned(0, 2), 2);
+        assert_eq!(after_pinned(1, 2), 2);
+        assert_eq!(after_pinned(5, 2), 5);
+        assert_eq!(after_pinned(0, 0), 0);
+    }
+
+    #[test]
+    fn pinned_tabs_keep_to_their_origin() {
+        let mail = Some("https://mail.example.com/inbox");
+        assert!(!leaves_pinned_site(mail, "https://mail.example.com/sent"));
+        assert!(leaves_pinned_site(mail, "https://example.com/"));
+        assert!(leaves_pinned_site(mail, "http://mail.example.com/"));
+        assert!(leaves_pinned_site(None, "https://example.com/"));
+    }
+}
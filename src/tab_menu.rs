@@ -0,0 +1,276 @@
+// --- TAB MENU ---
+use gtk::prelude::*;
+use gtk::{gio, Application, ApplicationWindow, Notebook, Widget};
+use webkit6::prelude::*;
+use webkit6::{WebView, WebViewSessionState};
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use crate::browser::Browser;
+use crate::tab_groups::TabGroups;
+use crate::tab_label::{self, TabLabel};
+use crate::tab_page;
+use crate::window::BrowserWindow;
+
+const MAX_CLOSED: usize = 25;
+
+// Window-level twins of the per-tab actions; they act on the current tab
+// and are what the keyboard shortcuts point at.
+const SHORTCUTS: [(&str, &[&str]); 8] = [
+    ("reload", &["<Primary>r", "F5"]),
+    ("duplicate", &[]),
+    ("toggle-pin", &[]),
+    ("toggle-mute", &["<Primary>m"]),
+    ("bookmark", &["<Primary>d"]),
+    ("close", &["<Primary>w"]),
+    ("close-others", &[]),
+    ("close-right", &[]),
+];
+
+// Incognito tabs are never remembered here.
+struct ClosedTab {
+    url: String,
+    state: Option<WebViewSessionState>,
+}
+
+// Shared by all windows. Tabs can be dragged between windows, so every
+// action finds the tab's current window when it runs.
+pub struct TabMenu {
+    browser: Weak<Browser>,
+    closed: RefCell<Vec<ClosedTab>>,
+    reopen_action: gio::SimpleAction,
+}
+
+// Newest last, so reopening pops it first; the oldest goes once full.
+fn remember<T>(closed: &mut Vec<T>, tab: T) {
+    closed.push(tab);
+    if closed.len() > MAX_CLOSED { closed.remove(0); }
+}
+
+fn pages(notebook: &Notebook) -> Vec<Widget> {
+    (0..notebook.n_pages()).filter_map(|i| notebook.nth_page(Some(i))).collect()
+}
+
+// Address and back/forward history of a tab, with or without its webview.
+fn snapshot(page: &Widget) -> (String, Option<WebViewSessionState>) {
+    match (tab_page::webview(page), tab_page::placeholder(page)) {
+        (Some(wv), _) => (wv.uri().map(|u| u.to_string()).unwrap_or_default(), Some(wv.session_state())),
+        (None, Some(p)) => (p.url.clone(), p.state.clone()),
+        (None, None) => (tab_label::header(page).map(|h| h.uri()).unwrap_or_default(), None),
+    }
+}
+
+// Back/forward history travels with the copy.
+pub fn restore_state(webview: &WebView, state: Option<&WebViewSessionState>, fallback_url: &str) {
+    if let Some(state) = state { webview.restore_session_state(state); }
+    match webview.back_forward_list().current_item() {
+        Some(item) => webview.go_to_back_forward_list_item(&item),
+        None => webview.load_uri(fallback_url),
+    }
+}
+
+impl TabMenu {
+    pub fn new(app: &Application, browser: Weak<Browser>) -> Rc<Self> {
+        let reopen_action = gio::SimpleAction::new("reopen-closed", None);
+        reopen_action.set_enabled(false);
+        app.add_action(&reopen_action);
+        app.set_accels_for_action("app.reopen-closed", &["<Primary><Shift>t"]);
+        let this = Rc::new(Self { browser, closed: RefCell::new(Vec::new()), reopen_action });
+        let weak = Rc::downgrade(&this);
+        this.reopen_action.connect_activate(move |_, _| {
+            if let Some(this) = weak.upgrade() { this.reopen(); }
+        });
+        this
+    }
+
+    fn changed(&self) {
+        if let Some(browser) = self.browser.upgrade() { browser.save_session(); }
+    }
+
+    pub fn close(&self, page: &Widget) {
+        let window = match BrowserWindow::of(page) { Some(w) => w, None => return };
+        let incognito = tab_label::header(page).map_or(true, |h| h.is_incognito());
+        let (url, state) = snapshot(page);
+        if !incognito && !url.is_empty() {
+            remember(&mut self.closed.borrow_mut(), ClosedTab { url, state });
+            self.reopen_action.set_enabled(true);
+        }
+        let notebook = window.notebook();
+        if let Some(idx) = notebook.page_num(page) { notebook.remove_page(Some(idx)); }
+        self.changed();
+    }
+
+    // Into the window in front, whichever one the tab was closed in.
+    pub fn reopen(&self) {
+        let browser = match self.browser.upgrade() { Some(b) => b, None => return };
+        let tab = match self.closed.borrow_mut().pop() { Some(t) => t, None => return };
+        self.reopen_action.set_enabled(!self.closed.borrow().is_empty());
+        let window = browser.active_window().unwrap_or_else(|| browser.new_window());
+        let webview = window.create_tab("", false);
+        restore_state(&webview, tab.state.as_ref(), &tab.url);
+        window.present();
+    }
+
+    pub fn duplicate(&self, page: &Widget) {
+        let window = match BrowserWindow::of(page) { Some(w) => w, None => return };
+        let incognito = tab_label::header(page).map_or(false, |h| h.is_incognito());
+        let (url, state) = snapshot(page);
+        let copy = window.open_tab("", incognito, false);
+        restore_state(&copy, state.as_ref(), &url);
+    }
+
+    fn close_where(&self, page: &Widget, keep: impl Fn(usize, &Widget) -> bool) {
+        let window = match BrowserWindow::of(page) { Some(w) => w, None => return };
+        for (i, other) in pages(window.notebook()).iter().enumerate() {
+            let pinned = tab_label::header(other).map_or(false, |h| h.is_pinned());
+            if !pinned && !keep(i, other) { self.close(other); }
+        }
+    }
+
+    // A tab without a webview gets one back instead.
+    fn reload(&self, page: &Widget) {
+        match tab_page::webview(page) {
+            Some(wv) => wv.reload(),
+            None => if let Some(browser) = self.browser.upgrade() { browser.wake(page); },
+        }
+    }
+
+    fn toggle_pin(&self, page: &Widget) {
+        let window = match BrowserWindow::of(page) { Some(w) => w, None => return };
+        let pinned = tab_label::header(page).map_or(false, |h| h.is_pinned());
+        if !pinned { window.groups().remove(page); }
+        tab_label::set_pinned(window.notebook(), page, !pinned);
+        self.changed();
+    }
+
+    fn toggle_bookmark(&self, page: &Widget) {
+        let (browser, header) = match (self.browser.upgrade(), tab_label::header(page)) { (Some(b), Some(h)) => (b, h), _ => return };
+        let uri = header.uri();
+        if uri.is_empty() { return; }
+        browser.bookmarks.borrow_mut().toggle(&uri, &header.title());
+        if let (Some(window), Some(wv)) = (BrowserWindow::of(page), tab_page::webview(page)) { window.show_page_icons(&wv); }
+    }
+
+    fn move_to_new_window(&self, page: &Widget) {
+        if let Some(browser) = self.browser.upgrade() { browser.move_to_new_window(&[page.clone()], None); }
+    }
+
+    fn move_group_to_new_window(&self, page: &Widget) {
+        let (browser, window) = match (self.browser.upgrade(), BrowserWindow::of(page)) { (Some(b), Some(w)) => (b, w), _ => return };
+        let id = match tab_label::header(page).and_then(|h| h.group()) { Some(id) => id, None => return };
+        let group = window.groups().groups().into_iter().find(|g| g.id == id);
+        browser.move_to_new_window(&window.groups().members(id), group);
+    }
+
+    fn model(&self, page: &Widget, header: &TabLabel) -> gio::Menu {
+        let menu = gio::Menu::new();
+        let muted = tab_page::webview(page).map_or(false, |wv| wv.is_muted());
+
+        let actions = gio::Menu::new();
+        actions.append(Some("Reload"), Some("tab.reload"));
+        actions.append(Some("Duplicate Tab"), Some("tab.duplicate"));
+        actions.append(Some(if header.is_pinned() { "Unpin Tab" } else { "Pin Tab" }), Some("tab.toggle-pin"));
+        actions.append(Some(if muted { "Unmute Tab" } else { "Mute Tab" }), Some("tab.toggle-mute"));
+        let bookmarked = self.browser.upgrade().map_or(false, |b| b.bookmarks.borrow().contains(&header.uri()));
+        actions.append(Some(if bookmarked { "Remove Bookmark" } else { "Bookmark Tab" }), Some("tab.bookmark"));
+        menu.append_section(None, &actions);
+
+        if let Some(window) = BrowserWindow::of(page) { menu.append_section(None, &window.groups().menu_section(page)); }
+
+        let windows = gio::Menu::new();
+        if !header.is_pinned() { windows.append(Some("Move Tab to New Window"), Some("tab.move-window")); }
+        windows.append(Some("New Window"), Some("app.new-window"));
+        menu.append_section(None, &windows);
+
+        let close = gio::Menu::new();
+        close.append(Some("Close Tab"), Some("tab.close"));
+        close.append(Some("Close Other Tabs"), Some("tab.close-others"));
+        close.append(Some("Close Tabs to the Right"), Some("tab.close-right"));
+        close.append(Some("Reopen Closed Tab"), Some("app.reopen-closed"));
+        menu.append_section(None, &close);
+        menu
+    }
+
+    // Once per tab. The actions act on the tab's page, so they keep working
+    // while the tab has no webview and after it gets a new one.
+    pub fn attach(self: &Rc<Self>, page: &Widget, header: &Rc<TabLabel>) {
+        let actions = gio::SimpleActionGroup::new();
+        let add = |name: &str, f: fn(&TabMenu, &Widget)| {
+            let action = gio::SimpleAction::new(name, None);
+            let this = Rc::downgrade(self);
+            let page = page.downgrade();
+            action.connect_activate(move |_, _| {
+                if let (Some(this), Some(page)) = (this.upgrade(), page.upgrade()) { f(&this, &page); }
+            });
+            actions.add_action(&action);
+        };
+        add("reload", |this, page| this.reload(page));
+        add("duplicate", |this, page| this.duplicate(page));
+        add("toggle-pin", |this, page| this.toggle_pin(page));
+        add("toggle-mute", |_, page| {
+            if let Some(wv) = tab_page::webview(page) { wv.set_is_muted(!wv.is_muted()); }
+        });
+        add("bookmark", |this, page| this.toggle_bookmark(page));
+        add("move-window", |this, page| this.move_to_new_window(page));
+        add("move-group-window", |this, page| this.move_group_to_new_window(page));
+        add("close", |this, page| this.close(page));
+        add("close-others", |this, page| this.close_where(page, |_, other| other == page));
+        add("close-right", |this, page| {
+            let own = BrowserWindow::of(page).and_then(|w| w.notebook().page_num(page)).unwrap_or(0) as usize;
+            this.close_where(page, |i, _| i <= own);
+        });
+        let this = Rc::downgrade(self);
+        TabGroups::install_actions(&actions, page, Rc::new(move || {
+            if let Some(this) = this.upgrade() { this.changed(); }
+        }));
+
+        let this = Rc::downgrade(self);
+        let header_menu = Rc::downgrade(header);
+        let page_menu = page.downgrade();
+        header.set_menu(&actions, move || {
+            match (this.upgrade(), header_menu.upgrade(), page_menu.upgrade()) {
+                (Some(this), Some(h), Some(page)) => this.model(&page, &h),
+                _ => gio::Menu::new(),
+            }
+        });
+        // For the placeholder's reload button.
+        page.insert_action_group("tab", Some(&actions));
+    }
+
+    pub fn install_window_actions(app: &Application, window: &ApplicationWindow, notebook: &Notebook) {
+        for (name, accels) in SHORTCUTS.iter() {
+            let action = gio::SimpleAction::new(&format!("tab-{}", name), None);
+            let notebook = notebook.downgrade();
+            let tab_action = name.to_string();
+            action.connect_activate(move |_, _| {
+                let current = notebook.upgrade().and_then(|nb| nb.nth_page(nb.current_page()));
+                if let Some(header) = current.as_ref().and_then(|p| tab_label::header(p)) { header.activate(&tab_action); }
+            });
+            window.add_action(&action);
+            app.set_accels_for_action(&format!("win.tab-{}", name), accels);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{remember, MAX_CLOSED};
+
+    #[test]
+    fn the_last_closed_tab_reopens_first() {
+        let mut list = Vec::new();
+        remember(&mut list, "https://a.example/");
+        remember(&mut list, "https://b.example/");
+        assert_eq!(list.pop(), Some("https://b.example/"));
+        assert_eq!(list.pop(), Some("https://a.example/"));
+    }
+
+    #[test]
+    fn only_the_newest_closed_tabs_are_kept() {
+        let mut list = Vec::new();
+        for i in 0..MAX_CLOSED + 3 { remember(&mut list, i); }
+        assert_eq!(list.len(), MAX_CLOSED);
+        assert_eq!(list[0], 3);
+        assert_eq!(list[MAX_CLOSED - 1], MAX_CLOSED + 2);
+    }
+}
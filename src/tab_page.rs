@@ -0,0 +1,109 @@
+// --- TAB PAGES ---
+use gtk::prelude::*;
+use gtk::{gio, glib, pango, Align, Box, Button, Image, Label, Orientation, Widget};
+use webkit6::prelude::*;
+use webkit6::{LoadEvent, WebView, WebViewSessionState};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::tab_label;
+
+const DATA_KEY: &str = "rusty-tab-placeholder";
+
+// What a tab without a webview remembers, enough to bring the page back.
+pub struct Placeholder {
+    pub url: String,
+    pub title: String,
+    pub state: Option<WebViewSessionState>,
+    pub scroll: Option<(f64, f64)>,
+}
+
+// Each notebook page is a box around the tab's webview, so the webview can
+// be dropped and rebuilt without the tab leaving the notebook.
+pub fn new() -> Box {
+    let page = Box::new(Orientation::Vertical, 0);
+    page.set_hexpand(true);
+    page.set_vexpand(true);
+    page
+}
+
+// The page for a page or for the webview inside one.
+pub fn page(widget: &impl IsA<Widget>) -> Option<Box> {
+    let widget = widget.as_ref();
+    let page = if widget.is::<WebView>() { widget.parent()? } else { widget.clone() };
+    page.downcast::<Box>().ok().filter(|p| tab_label::header(p).is_some())
+}
+
+pub fn webview(page: &impl IsA<Widget>) -> Option<WebView> {
+    page.as_ref().first_child().and_then(|c| c.downcast::<WebView>().ok())
+}
+
+pub fn placeholder(page: &impl IsA<Widget>) -> Option<Rc<Placeholder>> {
+    unsafe { page.as_ref().data::<Rc<Placeholder>>(DATA_KEY).map(|p| p.as_ref().clone()) }
+}
+
+fn clear(page: &Box) {
+    while let Some(child) = page.first_child() { page.remove(&child); }
+}
+
+pub fn set_webview(page: &Box, webview: &WebView) {
+    clear(page);
+    unsafe { let _ = page.steal_data::<Rc<Placeholder>>(DATA_KEY); }
+    webview.set_hexpand(true);
+    webview.set_vexpand(true);
+    page.append(webview);
+}
+
+// Drops the webview (and with it the web process) for a stand-in page that
+// shows what the tab was. The tab label keeps its title and favicon.
+pub fn set_placeholder(page: &Box, placeholder: Placeholder, message: &str) {
+    clear(page);
+    let header = tab_label::header(page);
+    if let Some(h) = &header { h.show_placeholder(&placeholder.url, &placeholder.title); }
+
+    let content = Box::new(Orientation::Vertical, 12);
+    content.set_halign(Align::Center);
+    content.set_valign(Align::Center);
+    content.set_vexpand(true);
+    content.add_css_class("tab-placeholder");
+    let icon = match header.and_then(|h| h.favicon()) {
+        Some(paintable) => Image::from_paintable(Some(&paintable)),
+        None => Image::from_icon_name("web-browser-symbolic"),
+    };
+    icon.set_pixel_size(48);
+    content.append(&icon);
+    let title = Label::new(Some(if placeholder.title.is_empty() { &placeholder.url } else { &placeholder.title }));
+    title.add_css_class("title-2");
+    title.set_ellipsize(pango::EllipsizeMode::End);
+    title.set_max_width_chars(60);
+    content.append(&title);
+    let text = Label::new(Some(message));
+    text.set_wrap(true);
+    content.append(&text);
+    let reload = Button::builder().label("Reload").action_name("tab.reload").halign(Align::Center).build();
+    content.append(&reload);
+    page.append(&content);
+
+    unsafe { page.set_data(DATA_KEY, Rc::new(placeholder)); }
+}
+
+// Where the page was scrolled to, as "x,y"; None for pages that can't say.
+pub fn read_scroll(webview: &WebView, done: impl FnOnce(Option<(f64, f64)>) + 'static) {
+    webview.evaluate_javascript("window.scrollX + ',' + window.scrollY", None, None, gio::Cancellable::NONE, move |result| {
+        let text = result.ok().map(|v| v.to_str().to_string());
+        let scroll = text.as_deref().and_then(|t| t.split_once(',')).and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)));
+        done(scroll);
+    });
+}
+
+// Restores the offset once, after the first load finishes.
+pub fn scroll_after_load(webview: &WebView, x: f64, y: f64) {
+    let handler: Rc<RefCell<Option<glib::SignalHandlerId>>> = Rc::new(RefCell::new(None));
+    let handler_once = handler.clone();
+    let id = webview.connect_load_changed(move |wv, event| {
+        if event != LoadEvent::Finished { return; }
+        if let Some(id) = handler_once.borrow_mut().take() { wv.disconnect(id); }
+        wv.evaluate_javascript(&format!("window.scrollTo({}, {})", x, y), None, None, gio::Cancellable::NONE, |_| {});
+    });
+    *handler.borrow_mut() = Some(id);
+}
@@ -0,0 +1,235 @@
+// --- TLS ERRORS & CERTIFICATES ---
+use gtk::prelude::*;
+use gtk::{gio, glib, Align, Box, Label, Notebook, Orientation, PolicyType, ScrolledWindow, Separator, TextView, Window, WrapMode};
+use webkit6::prelude::*;
+use webkit6::{LoadEvent, PolicyDecisionType, WebView};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::navigation_uri;
+use crate::pages;
+use crate::tab_page;
+
+// The error page's "proceed" link points here; decide-policy catches it.
+const PROCEED_PREFIX: &str = "rusty-tls-proceed:";
+
+fn describe_errors(flags: gio::TlsCertificateFlags) -> Vec<(&'static str, &'static str)> {
+    let known = [
+        (gio::TlsCertificateFlags::UNKNOWN_CA, "Untrusted issuer", "The certificate isn't signed by an authority this system trusts. It may be self-signed."),
+        (gio::TlsCertificateFlags::BAD_IDENTITY, "Wrong site", "The certificate was issued for a different domain than the one you're visiting."),
+        (gio::TlsCertificateFlags::NOT_ACTIVATED, "Not yet valid", "The certificate's validity period hasn't started. Check your system clock."),
+        (gio::TlsCertificateFlags::EXPIRED, "Expired", "The certificate has expired."),
+        (gio::TlsCertificateFlags::REVOKED, "Revoked", "The issuer has revoked this certificate."),
+        (gio::TlsCertificateFlags::INSECURE, "Insecure algorithm", "The certificate uses a signature algorithm that is no longer considered secure."),
+        (gio::TlsCertificateFlags::GENERIC_ERROR, "Invalid certificate", "The certificate could not be validated."),
+    ];
+    known.iter().filter(|(flag, _, _)| flags.contains(*flag)).map(|(_, title, text)| (*title, *text)).collect()
+}
+
+fn error_page_html(uri: &str, flags: gio::TlsCertificateFlags) -> String {
+    let host = glib::Uri::parse(uri, glib::UriFlags::NONE).ok().and_then(|u| u.host()).map(|h| h.to_string()).unwrap_or_default();
+    let reasons: String = describe_errors(flags)
+        .into_iter()
+        .map(|(title, text)| format!("<li><b>{}</b> — {}</li>", title, text))
+        .collect();
+    pages::render("Certificate error", &format!(r#"
+    <h1 class="danger">Your connection isn't private</h1>
+    <p>The certificate presented by <code>{host}</code> failed verification. Someone may be trying to intercept your connection.</p>
+    <ul>{reasons}</ul>
+    <a class="button primary" href="javascript:history.back()">Go Back</a>
+    <details>
+        <summary>Advanced</summary>
+        <p>Proceeding trusts this certificate for <code>{host}</code> until the browser closes.</p>
+        <a class="button danger-button" href="{prefix}{uri}">Proceed Anyway (unsafe)</a>
+    </details>"#,
+        host = glib::markup_escape_text(&host),
+        reasons = reasons,
+        prefix = PROCEED_PREFIX,
+        uri = glib::markup_escape_text(uri)))
+}
+
+// Only for the address that failed, from the tab still showing its error page.
+fn proceed_allowed(failing_uri: Option<&str>, target: &str, current_uri: Option<&str>) -> bool {
+    failing_uri.map_or(false, |failing| failing == target && current_uri == Some(failing))
+}
+
+// Shows the error page on TLS failures and handles its "proceed" link by
+// trusting that one certificate for that one host in the tab's network session.
+// Any page can link to the proceed scheme, so it only counts while this tab
+// shows the error page, and only for the address that failed.
+pub fn attach(webview: &WebView) {
+    let failed: Rc<RefCell<Option<(String, gio::TlsCertificate)>>> = Rc::new(RefCell::new(None));
+
+    let failed_tls = failed.clone();
+    webview.connect_load_failed_with_tls_errors(move |wv, failing_uri, certificate, errors| {
+        *failed_tls.borrow_mut() = Some((failing_uri.to_string(), certificate.clone()));
+        wv.load_alternate_html(&error_page_html(failing_uri, errors), failing_uri, None);
+        true
+    });
+
+    // The error page commits under the failing address; anything else ends it.
+    let failed_load = failed.clone();
+    webview.connect_load_changed(move |wv, event| {
+        if event != LoadEvent::Committed { return; }
+        let shown = failed_load.borrow().as_ref().map(|(failing_uri, _)| failing_uri.clone());
+        if shown.is_some() && wv.uri().map(|u| u.to_string()) != shown { *failed_load.borrow_mut() = None; }
+    });
+
+    webview.connect_decide_policy(move |wv, decision, decision_type| {
+        if decision_type != PolicyDecisionType::NavigationAction { return false; }
+        let uri = match navigation_uri(decision) { Some(u) => u, None => return false };
+        let target = match uri.strip_prefix(PROCEED_PREFIX) { Some(t) => t.to_string(), None => return false };
+        decision.ignore();
+
+        let failing_uri = failed.borrow().as_ref().map(|(failing_uri, _)| failing_uri.clone());
+        if !proceed_allowed(failing_uri.as_deref(), &target, wv.uri().as_deref()) { return true; }
+        if let Some((failing_uri, certificate)) = failed.borrow_mut().take() {
+            let host = glib::Uri::parse(&failing_uri, glib::UriFlags::NONE).ok().and_then(|u| u.host());
+            if let Some(host) = host {
+                wv.network_session().allow_tls_certificate_for_host(&certificate, &host);
+                wv.load_uri(&target);
+            }
+        }
+        true
+    });
+}
+
+// Primary icon for the omnibar: padlock for verified https, a warning for
+// plain http or a certificate we were told to accept anyway.
+pub fn security_icon(webview: &WebView) -> Option<&'static str> {
+    let uri = webview.uri()?;
+    icon_for(&uri, webview.tls_info().map(|(_, flags)| flags))
+}
+
+fn icon_for(uri: &str, tls_errors: Option<gio::TlsCertificateFlags>) -> Option<&'static str> {
+    if uri.starts_with("https://") {
+        match tls_errors {
+            Some(flags) if flags.is_empty() => Some("channel-secure-symbolic"),
+            _ => Some("channel-insecure-symbolic"),
+        }
+    } else if uri.starts_with("http://") {
+        Some("channel-insecure-symbolic")
+    } else {
+        None
+    }
+}
+
+pub fn update_security_icon(url_bar: &gtk::Entry, webview: &WebView) {
+    url_bar.set_icon_from_icon_name(gtk::EntryIconPosition::Primary, security_icon(webview));
+    url_bar.set_icon_tooltip_text(gtk::EntryIconPosition::Primary, Some("View Certificate"));
+}
+
+pub fn current_webview(notebook: &Notebook) -> Option<WebView> {
+    notebook.nth_page(notebook.current_page()).and_then(|p| tab_page::webview(&p))
+}
+
+// Newer GLib adds these as properties; older ones simply don't show them.
+fn cert_string(cert: &gio::TlsCertificate, name: &str) -> Option<String> {
+    cert.find_property(name)?;
+    cert.property::<Option<String>>(name)
+}
+
+fn cert_date(cert: &gio::TlsCertificate, name: &str) -> Option<String> {
+    cert.find_property(name)?;
+    cert.property::<Option<glib::DateTime>>(name).and_then(|d| d.format("%Y-%m-%d %H:%M UTC").ok()).map(|s| s.to_string())
+}
+
+fn certificate_section(cert: &gio::TlsCertificate, depth: usize) -> Box {
+    let vbox = Box::new(Orientation::Vertical, 6);
+    let title = if depth == 0 { "Site Certificate".to_string() } else { format!("Issuer #{}", depth) };
+    let header = Label::new(None); header.set_markup(&format!("<b>{}</b>", title)); header.set_halign(Align::Start); vbox.append(&header);
+
+    let fields = [
+        ("Subject", cert_string(cert, "subject-name")),
+        ("Issuer", cert_string(cert, "issuer-name")),
+        ("Valid From", cert_date(cert, "not-valid-before")),
+        ("Valid Until", cert_date(cert, "not-valid-after")),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            let lbl = Label::new(Some(&format!("{}: {}", name, value)));
+            lbl.set_halign(Align::Start); lbl.set_wrap(true); lbl.set_selectable(true);
+            vbox.append(&lbl);
+        }
+    }
+
+    if let Some(pem) = cert.property::<Option<String>>("certificate-pem") {
+        let view = TextView::new();
+        view.set_editable(false);
+        view.set_monospace(true);
+        view.set_wrap_mode(WrapMode::Char);
+        view.buffer().set_text(&pem);
+        let scroll = ScrolledWindow::builder().hscrollbar_policy(PolicyType::Never).min_content_height(120).child(&view).build();
+        vbox.append(&scroll);
+    }
+    vbox
+}
+
+pub fn show_certificate_viewer(parent: &impl IsA<Window>, webview: &WebView) {
+    let window = Window::builder().transient_for(parent).title("Certificate Viewer").default_width(560).default_height(600).build();
+    let vbox = Box::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(20); vbox.set_margin_bottom(20); vbox.set_margin_start(20); vbox.set_margin_end(20);
+
+    let uri = webview.uri().map(|u| u.to_string()).unwrap_or_default();
+    let site = Label::new(Some(&uri)); site.set_halign(Align::Start); site.set_wrap(true); vbox.append(&site);
+
+    match webview.tls_info() {
+        Some((certificate, flags)) => {
+            let status = if flags.is_empty() {
+                "The connection is encrypted and the certificate is valid.".to_string()
+            } else {
+                let reasons: Vec<&str> = describe_errors(flags).into_iter().map(|(title, _)| title).collect();
+                format!("Certificate accepted despite errors: {}", reasons.join(", "))
+            };
+            let status_lbl = Label::new(Some(&status)); status_lbl.set_halign(Align::Start); status_lbl.set_wrap(true); vbox.append(&status_lbl);
+
+            let mut cert = Some(certificate);
+            let mut depth = 0;
+            while let Some(current) = cert {
+                vbox.append(&Separator::new(Orientation::Horizontal));
+                vbox.append(&certificate_section(&current, depth));
+                cert = current.issuer();
+                depth += 1;
+            }
+        }
+        None => {
+            vbox.append(&Label::new(Some("This page was not loaded over a secure connection.")));
+        }
+    }
+
+    let scroll = ScrolledWindow::builder().hscrollbar_policy(PolicyType::Never).child(&vbox).build();
+    window.set_child(Some(&scroll));
+    window.present();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe_errors, icon_for, proceed_allowed};
+    use gtk::gio::TlsCertificateFlags;
+
+    #[test]
+    fn each_certificate_problem_is_described_once() {
+        assert!(describe_errors(TlsCertificateFlags::empty()).is_empty());
+        let titles: Vec<&str> = describe_errors(TlsCertificateFlags::EXPIRED | TlsCertificateFlags::UNKNOWN_CA)
+            .into_iter().map(|(title, _)| title).collect();
+        assert_eq!(titles, ["Untrusted issuer", "Expired"]);
+    }
+
+    #[test]
+    fn proceed_needs_the_failing_address_on_screen() {
+        let failing = "https://self-signed.example/";
+        assert!(proceed_allowed(Some(failing), failing, Some(failing)));
+        assert!(!proceed_allowed(None, failing, Some(failing)));
+        assert!(!proceed_allowed(Some(failing), "https://other.example/", Some(failing)));
+        assert!(!proceed_allowed(Some(failing), failing, Some("https://attacker.example/")));
+    }
+
+    #[test]
+    fn padlock_only_for_verified_https() {
+        assert_eq!(icon_for("https://example.com/", Some(TlsCertificateFlags::empty())), Some("channel-secure-symbolic"));
+        assert_eq!(icon_for("https://example.com/", Some(TlsCertificateFlags::EXPIRED)), Some("channel-insecure-symbolic"));
+        assert_eq!(icon_for("https://example.com/", None), Some("channel-insecure-symbolic"));
+        assert_eq!(icon_for("http://example.com/", None), Some("channel-insecure-symbolic"));
+        assert_eq!(icon_for("rusty:history", None), None);
+    }
+}
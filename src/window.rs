@@ -0,0 +1,742 @@
+// --- BROWSER WINDOW ---
+use gtk::prelude::*;
+use gtk::{glib, ApplicationWindow, Box, Orientation, Entry, Button, Label, Dialog, ResponseType, Switch, Separator, LinkButton,
+    ScrolledWindow, PolicyType, ProgressBar, DropDown, StringList, Image, Window, MenuButton, Notebook, Popover, Widget};
+use gtk::gdk_pixbuf::PixbufLoader;
+use gtk::gdk;
+use webkit6::prelude::*;
+use webkit6::WebView;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use crate::browser::Browser;
+use crate::permissions::{self, PermissionPrompts};
+use crate::session::{SavedTab, WindowSession};
+use crate::sidebar::TabSidebar;
+use crate::tab_groups::{TabGroup, TabGroups};
+use crate::tab_label;
+use crate::tab_menu::TabMenu;
+use crate::tab_page;
+use crate::{bookmarks, clear_data, default_browser, favicons, hibernation, https_only, internal, newtab, site_data, tls};
+use crate::{is_amnesia_session, save_amnesia_preference, save_config, AppConfig, SEARCH_ENGINES};
+
+// Shared by every window's notebook, so tabs can be dragged between them.
+const TAB_GROUP_NAME: &str = "rusty-tabs";
+const DATA_KEY: &str = "rusty-browser-window";
+const POPUP_KEY: &str = "rusty-popup-host";
+
+// What a pop-up window has instead of a BrowserWindow: its own prompt bar,
+// and the window it was opened from, which takes the tabs it opens.
+struct PopupHost {
+    prompts: Rc<PermissionPrompts>,
+    opener: Weak<BrowserWindow>,
+}
+
+// One toplevel: toolbar, tab strip and the tabs currently in it.
+pub struct BrowserWindow {
+    browser: Rc<Browser>,
+    window: ApplicationWindow,
+    notebook: Notebook,
+    url_bar: Entry,
+    site_icon: Image,
+    progress_bar: ProgressBar,
+    home_btn: Button,
+    prompts: Rc<PermissionPrompts>,
+    groups: Rc<TabGroups>,
+    sidebar: Rc<TabSidebar>,
+    // The tab links were last opened from and the newest of them, so a run of
+    // background tabs keeps its order instead of stacking up in reverse.
+    last_opened: RefCell<Option<(glib::WeakRef<Widget>, glib::WeakRef<Widget>)>>,
+    // Set while a session is restored, so the notebook selecting its first
+    // page along the way doesn't load that tab.
+    restoring: Cell<bool>,
+}
+
+// Only the selected tab loads while a session is restored; with nothing
+// selected, none do and a home page tab is added instead.
+fn loads_on_restore(index: usize, active: Option<usize>) -> bool {
+    active == Some(index)
+}
+
+fn update_bookmark_icon(url_bar: &Entry, webview: &WebView, bookmarks: &bookmarks::Bookmarks) {
+    let (icon, tip) = match webview.uri() {
+        Some(uri) if bookmarks.contains(&uri) => (Some("starred-symbolic"), "Remove Bookmark"),
+        Some(_) => (Some("non-starred-symbolic"), "Bookmark This Page"),
+        None => (None, ""),
+    };
+    url_bar.set_icon_from_icon_name(gtk::EntryIconPosition::Secondary, icon);
+    url_bar.set_icon_tooltip_text(gtk::EntryIconPosition::Secondary, Some(tip));
+}
+
+// Re-asked every time; another browser may have taken over since.
+fn show_default_status(label: &Label, button: &Button) {
+    let (label, button) = (label.downgrade(), button.downgrade());
+    default_browser::check(move |status| {
+        let (label, button) = match (label.upgrade(), button.upgrade()) { (Some(l), Some(b)) => (l, b), _ => return };
+        label.set_text(match status {
+            Some(true) => "Rusty Browser is your default browser",
+            Some(false) => "Rusty Browser is not your default browser",
+            None => "Default browser status unavailable (xdg-settings not found)",
+        });
+        button.set_sensitive(status != Some(true) && !is_amnesia_session());
+        if is_amnesia_session() { button.set_tooltip_text(Some("Not available in Amnesia Mode")); }
+    });
+}
+
+// Url bar input (or a command-line argument) to the address to load.
+pub fn resolve_input(input: &str, state: &AppConfig) -> String {
+    if input.contains("://") || internal::is_internal(input) || input.starts_with("about:") { input.to_string() }
+    else if !input.contains('.') || input.contains(' ') { format!("{}{}", state.search_engine_url, input) }
+    else { format!("https://{}", input) }
+}
+
+impl BrowserWindow {
+    // The window a widget (usually a tab's webview) currently lives in.
+    pub fn of(widget: &impl IsA<Widget>) -> Option<Rc<Self>> {
+        let root = widget.as_ref().root()?;
+        unsafe { root.data::<Weak<BrowserWindow>>(DATA_KEY).and_then(|p| p.as_ref().upgrade()) }
+    }
+
+    fn popup_host(widget: &impl IsA<Widget>) -> Option<Rc<PopupHost>> {
+        let root = widget.as_ref().root()?;
+        unsafe { root.data::<Rc<PopupHost>>(POPUP_KEY).map(|p| p.as_ref().clone()) }
+    }
+
+    // Where links a widget opens in new tabs go: its own window, or for a
+    // pop-up, the window that opened it.
+    pub fn for_new_tabs(widget: &impl IsA<Widget>) -> Option<Rc<Self>> {
+        Self::of(widget).or_else(|| Self::popup_host(widget)?.opener.upgrade())
+    }
+
+    // The prompt bar that asks on a widget's behalf.
+    pub fn prompts_for(widget: &impl IsA<Widget>) -> Option<Rc<PermissionPrompts>> {
+        match Self::of(widget) {
+            Some(window) => Some(window.prompts.clone()),
+            None => Self::popup_host(widget).map(|host| host.prompts.clone()),
+        }
+    }
+
+    // Fills a pop-up window with a page and a prompt bar of its own.
+    pub fn host_popup(popup: &Window, page: &Box, opener: Option<&Rc<Self>>) {
+        let prompts = PermissionPrompts::standalone();
+        let content = Box::new(Orientation::Vertical, 0);
+        content.append(prompts.widget());
+        content.append(page);
+        popup.set_child(Some(&content));
+        let host = Rc::new(PopupHost { prompts, opener: opener.map_or_else(Weak::new, Rc::downgrade) });
+        unsafe { popup.set_data(POPUP_KEY, host); }
+    }
+
+    pub fn new(browser: &Rc<Browser>) -> Rc<Self> {
+        let window = ApplicationWindow::builder()
+            .title("Rusty Browser")
+            .default_width(1200)
+            .default_height(800)
+            .build();
+        window.set_application(browser.app().as_ref());
+
+        let main_box = Box::new(Orientation::Vertical, 0);
+        window.set_child(Some(&main_box));
+
+        // --- TOOLBAR ---
+        let toolbar = Box::new(Orientation::Horizontal, 0);
+        toolbar.add_css_class("toolbar");
+
+        let back_btn = Button::builder().icon_name("go-previous-symbolic").tooltip_text("Go Back").build();
+        let forward_btn = Button::builder().icon_name("go-next-symbolic").tooltip_text("Go Forward").build();
+        let refresh_btn = Button::builder().icon_name("view-refresh-symbolic").tooltip_text("Reload").build();
+        let home_btn = Button::builder().icon_name("go-home-symbolic").tooltip_text("Home").build();
+
+        let page_info_popover = Popover::new();
+        let page_info_btn = MenuButton::builder().icon_name("dialog-information-symbolic").tooltip_text("Site Information").popover(&page_info_popover).build();
+
+        let site_icon = favicons::tab_image();
+        site_icon.set_margin_start(6);
+        site_icon.set_margin_end(0);
+
+        let url_bar = Entry::new();
+        url_bar.set_hexpand(true);
+        url_bar.set_placeholder_text(Some("Search or enter URL"));
+
+        let new_tab_btn = Button::builder().icon_name("tab-new-symbolic").tooltip_text("New Tab").build();
+        let incognito_btn = Button::builder().icon_name("weather-clear-night-symbolic").tooltip_text("Incognito").build();
+        incognito_btn.add_css_class("incognito-btn");
+        let settings_btn = Button::builder().icon_name("emblem-system-symbolic").tooltip_text("Settings").build();
+
+        toolbar.append(&back_btn);
+        toolbar.append(&forward_btn);
+        toolbar.append(&refresh_btn);
+        toolbar.append(&home_btn);
+        toolbar.append(&page_info_btn);
+        toolbar.append(&site_icon);
+        toolbar.append(&url_bar);
+        toolbar.append(&new_tab_btn);
+        toolbar.append(&incognito_btn);
+        toolbar.append(&settings_btn);
+        main_box.append(&toolbar);
+
+        let progress_bar = ProgressBar::new();
+        progress_bar.set_visible(false);
+        main_box.append(&progress_bar);
+
+        let notebook = Notebook::new();
+        notebook.set_scrollable(true);
+        notebook.set_vexpand(true);
+        notebook.set_group_name(Some(TAB_GROUP_NAME));
+        let prompts = PermissionPrompts::new(&notebook);
+        main_box.append(prompts.widget());
+
+        // --- VERTICAL TABS ---
+        let groups = TabGroups::new(&notebook);
+        let state_sidebar = browser.state.clone();
+        let sidebar = TabSidebar::new(&notebook, groups.clone(), browser.state.borrow().vertical_tabs_collapsed, move |collapsed| {
+            let mut state = state_sidebar.borrow_mut();
+            state.vertical_tabs_collapsed = collapsed;
+            save_config(&state);
+        });
+        let content_box = Box::new(Orientation::Horizontal, 0);
+        content_box.append(sidebar.widget());
+        content_box.append(&notebook);
+        notebook.set_hexpand(true);
+        main_box.append(&content_box);
+
+        let this = Rc::new(Self { browser: browser.clone(), window, notebook, url_bar, site_icon, progress_bar, home_btn, prompts, groups, sidebar, last_opened: RefCell::new(None), restoring: Cell::new(false) });
+        unsafe { this.window.set_data(DATA_KEY, Rc::downgrade(&this)); }
+        this.apply_settings();
+        if let Some(app) = browser.app() { TabMenu::install_window_actions(&app, &this.window, &this.notebook); }
+
+        // --- ACTIONS ---
+        let weak = Rc::downgrade(&this);
+        new_tab_btn.connect_clicked(move |_| {
+            if let Some(this) = weak.upgrade() { let url = newtab::new_tab_url(&this.browser.state.borrow()); this.open_tab(&url, false, false); }
+        });
+
+        let weak = Rc::downgrade(&this);
+        incognito_btn.connect_clicked(move |_| {
+            if let Some(this) = weak.upgrade() { let url = newtab::new_tab_url(&this.browser.state.borrow()); this.open_tab(&url, true, false); }
+        });
+
+        let weak = Rc::downgrade(&this);
+        this.url_bar.connect_activate(move |entry| {
+            let this = match weak.upgrade() { Some(t) => t, None => return };
+            if let Some(webview) = this.current() {
+                let target_url = resolve_input(&entry.text(), &this.browser.state.borrow());
+                webview.load_uri(&target_url);
+            }
+        });
+
+        let weak = Rc::downgrade(&this);
+        back_btn.connect_clicked(move |_| {
+            if let Some(webview) = weak.upgrade().and_then(|t| t.current()) { if webview.can_go_back() { webview.go_back(); } }
+        });
+
+        let weak = Rc::downgrade(&this);
+        forward_btn.connect_clicked(move |_| {
+            if let Some(webview) = weak.upgrade().and_then(|t| t.current()) { if webview.can_go_forward() { webview.go_forward(); } }
+        });
+
+        let weak = Rc::downgrade(&this);
+        refresh_btn.connect_clicked(move |_| {
+            if let Some(webview) = weak.upgrade().and_then(|t| t.current()) { webview.reload(); }
+        });
+
+        let weak = Rc::downgrade(&this);
+        this.home_btn.connect_clicked(move |_| {
+            if let Some(this) = weak.upgrade() {
+                if let Some(webview) = this.current() { webview.load_uri(&this.browser.state.borrow().homepage); }
+            }
+        });
+
+        let weak = Rc::downgrade(&this);
+        page_info_popover.connect_show(move |popover| {
+            if let Some(this) = weak.upgrade() {
+                let uri = this.current().and_then(|webview| webview.uri()).map(|u| u.to_string());
+                permissions::fill_page_info(popover, uri, &this.browser.permissions, &this.browser.notifications);
+            }
+        });
+
+        let weak = Rc::downgrade(&this);
+        this.url_bar.connect_icon_press(move |entry, pos| {
+            let this = match weak.upgrade() { Some(t) => t, None => return };
+            let webview = match this.current() { Some(w) => w, None => return };
+            if pos == gtk::EntryIconPosition::Primary {
+                tls::show_certificate_viewer(&this.window, &webview);
+            } else if let Some(uri) = webview.uri() {
+                let title = webview.title().map(|t| t.to_string()).unwrap_or_default();
+                this.browser.bookmarks.borrow_mut().toggle(&uri, &title);
+                update_bookmark_icon(entry, &webview, &this.browser.bookmarks.borrow());
+            }
+        });
+
+        let weak = Rc::downgrade(&this);
+        settings_btn.connect_clicked(move |_| {
+            if let Some(this) = weak.upgrade() { this.show_settings(); }
+        });
+
+        // --- TAB STRIP ---
+        // Prompts follow the tab that asked. Deferred because the notebook
+        // reports the new page only after switch-page returns.
+        let prompts_switch = this.prompts.clone();
+        this.notebook.connect_switch_page(move |_, _, _| {
+            let prompts = prompts_switch.clone();
+            glib::idle_add_local_once(move || prompts.sync());
+        });
+
+        // A tab without a webview gets one back as soon as it's selected.
+        let weak = Rc::downgrade(&this);
+        this.notebook.connect_switch_page(move |nb, widget, _| {
+            let this = match weak.upgrade() { Some(t) => t, None => return };
+            if this.restoring.get() { return; }
+            // Still the tab being left while the signal runs.
+            if let Some(left) = nb.nth_page(nb.current_page()).and_then(|p| tab_label::header(&p)) { left.touch(); }
+            if let Some(header) = tab_label::header(widget) { header.touch(); }
+            let webview = tab_page::webview(widget).or_else(|| this.browser.wake(widget));
+            if let Some(webview) = webview { this.show_tab(&webview); }
+        });
+
+        this.notebook.connect_page_reordered(|nb, page, position| tab_label::keep_pinned_first(nb, page, position));
+
+        // Runs for tabs created here and for tabs dragged in from another window.
+        let weak = Rc::downgrade(&this);
+        this.notebook.connect_page_added(move |nb, page, _| {
+            let (this, header) = match (weak.upgrade(), tab_label::header(page)) { (Some(t), Some(h)) => (t, h), _ => return };
+            nb.set_tab_reorderable(page, !header.is_pinned());
+            nb.set_tab_detachable(page, !header.is_pinned());
+            // Group ids are per window; a stranger's group doesn't come along.
+            if let Some(id) = header.group() {
+                if !this.groups.groups().iter().any(|g| g.id == id) { header.set_group(None); }
+            }
+            this.groups.refresh();
+        });
+
+        // A window left empty by dragging its last tab away closes itself.
+        let weak = Rc::downgrade(&this);
+        this.notebook.connect_page_removed(move |nb, page, _| {
+            if let (Some(this), Some(webview)) = (weak.upgrade(), tab_page::webview(page)) { this.prompts.dismiss(&webview); }
+            if nb.n_pages() > 0 { return; }
+            let (nb, page) = (nb.downgrade(), page.downgrade());
+            glib::idle_add_local_once(move || {
+                if let (Some(nb), Some(page)) = (nb.upgrade(), page.upgrade()) {
+                    if nb.n_pages() == 0 && page.parent().is_some() {
+                        if let Some(win) = nb.root().and_then(|r| r.downcast::<Window>().ok()) { win.close(); }
+                    }
+                }
+            });
+        });
+
+        let browser_weak = Rc::downgrade(browser);
+        this.notebook.connect_create_window(move |_, _| {
+            let window = browser_weak.upgrade()?.new_window();
+            window.present();
+            Some(window.notebook.clone())
+        });
+
+        let weak = Rc::downgrade(&this);
+        this.window.connect_close_request(move |_| {
+            match weak.upgrade() {
+                Some(this) => this.browser.window_closing(&this),
+                None => glib::signal::Inhibit(false),
+            }
+        });
+
+        this
+    }
+
+    pub fn window(&self) -> &ApplicationWindow {
+        &self.window
+    }
+
+    pub fn notebook(&self) -> &Notebook {
+        &self.notebook
+    }
+
+    pub fn groups(&self) -> &Rc<TabGroups> {
+        &self.groups
+    }
+
+    pub fn present(&self) {
+        self.window.present();
+    }
+
+    pub fn current(&self) -> Option<WebView> {
+        tls::current_webview(&self.notebook)
+    }
+
+    fn is_current(&self, webview: &WebView) -> bool {
+        self.current().as_ref() == Some(webview)
+    }
+
+    // Toolbar and tab strip options from settings.
+    pub fn apply_settings(&self) {
+        let state = self.browser.state.borrow();
+        self.home_btn.set_visible(state.show_home_button);
+        self.sidebar.set_enabled(state.vertical_tabs);
+    }
+
+    // --- TABS ---
+    pub fn create_tab(&self, url: &str, is_incognito: bool) -> WebView {
+        let webview = self.browser.create_webview(url, is_incognito);
+        self.adopt_tab(&webview);
+        webview
+    }
+
+    // A tab the user asked for from this window: the new-tab buttons, links
+    // and page menus. Placed according to the tab position setting.
+    pub fn open_tab(&self, url: &str, is_incognito: bool, background: bool) -> WebView {
+        let webview = self.browser.create_webview(url, is_incognito);
+        self.insert_tab(&webview, background);
+        webview
+    }
+
+    // Tab methods take a tab's page or the webview inside it.
+    pub fn insert_tab(&self, tab: &impl IsA<Widget>, background: bool) {
+        let (page, header) = match tab_page::page(tab).and_then(|p| tab_label::header(&p).map(|h| (p, h))) { Some(t) => t, None => return };
+        let current = self.notebook.nth_page(self.notebook.current_page());
+        let position = current.as_ref().and_then(|c| self.position_after(c, &page));
+        let page_idx = self.notebook.insert_page(&page, Some(header.widget()), position);
+        if background {
+            *self.last_opened.borrow_mut() = current.map(|c| (c.downgrade(), page.upcast_ref::<Widget>().downgrade()));
+        } else {
+            self.notebook.set_current_page(Some(page_idx));
+        }
+        self.notebook.show();
+    }
+
+    // Just after the current tab and the tabs already opened from it, past any
+    // pinned ones; None (the end) unless the setting asks for that.
+    fn position_after(&self, current: &Widget, page: &impl IsA<Widget>) -> Option<u32> {
+        if !self.browser.state.borrow().tabs_next_to_current { return None; }
+        let after = match &*self.last_opened.borrow() {
+            Some((opener, last)) if opener.upgrade().as_ref() == Some(current) => last.upgrade().filter(|l| self.notebook.page_num(l).is_some()),
+            _ => None,
+        };
+        let idx = self.notebook.page_num(after.as_ref().unwrap_or(current))?;
+        Some((idx + 1).max(tab_label::pinned_before(&self.notebook, page)))
+    }
+
+    // Appends a tab that already has its label, new or moved from another window.
+    pub fn adopt_tab(&self, tab: &impl IsA<Widget>) {
+        let (page, header) = match tab_page::page(tab).and_then(|p| tab_label::header(&p).map(|h| (p, h))) { Some(t) => t, None => return };
+        let page_idx = self.notebook.append_page(&page, Some(header.widget()));
+        self.notebook.set_current_page(Some(page_idx));
+        self.notebook.show();
+    }
+
+    // Takes a tab out without destroying it; the caller keeps it alive.
+    pub fn release_tab(&self, tab: &impl IsA<Widget>) {
+        if let Some(idx) = tab_page::page(tab).and_then(|p| self.notebook.page_num(&p)) { self.notebook.remove_page(Some(idx)); }
+    }
+
+    // Only the active tab gets a webview; the rest load when first selected.
+    pub fn restore(&self, tabs: &[SavedTab], groups: Vec<TabGroup>, active: Option<usize>) {
+        self.restoring.set(true);
+        for (i, tab) in tabs.iter().enumerate() {
+            let page = if loads_on_restore(i, active) {
+                tab_page::page(&self.browser.create_webview(&tab.url, false))
+            } else {
+                Some(self.browser.create_lazy_tab(&tab.url, &tab.title, false))
+            };
+            let (page, header) = match page.and_then(|p| tab_label::header(&p).map(|h| (p, h))) { Some(t) => t, None => continue };
+            self.notebook.append_page(&page, Some(header.widget()));
+            if tab.pinned { tab_label::set_pinned(&self.notebook, &page, true); }
+            header.set_group(tab.group);
+        }
+        self.restoring.set(false);
+        // After the tabs exist, or the empty groups would be pruned straight away.
+        self.groups.restore(groups);
+        self.groups.refresh();
+        self.notebook.show();
+        match active {
+            Some(idx) => self.notebook.set_current_page(Some(idx as u32)),
+            None => { let homepage = self.browser.state.borrow().homepage.clone(); self.create_tab(&homepage, false); }
+        }
+        // The notebook may already have been on that page, with no switch to load it.
+        if let Some(page) = self.notebook.nth_page(self.notebook.current_page()) {
+            if let Some(webview) = tab_page::webview(&page).or_else(|| self.browser.wake(&page)) { self.show_tab(&webview); }
+        }
+    }
+
+    pub fn capture(&self) -> WindowSession {
+        WindowSession::capture(&self.notebook, &self.groups)
+    }
+
+    // --- CHROME ---
+    // Everything the toolbar shows about the selected tab.
+    fn show_tab(&self, webview: &WebView) {
+        if let Some(uri) = webview.uri() { self.url_bar.set_text(newtab::display_uri(&uri)); }
+        favicons::update_image(&self.site_icon, webview);
+        tls::update_security_icon(&self.url_bar, webview);
+        update_bookmark_icon(&self.url_bar, webview, &self.browser.bookmarks.borrow());
+        match webview.title() {
+            Some(title) => self.window.set_title(Some(&format!("{} - Rusty Browser", title))),
+            None => self.window.set_title(Some("Rusty Browser")),
+        }
+        self.progress_bar.set_fraction(webview.estimated_load_progress());
+        self.progress_bar.set_visible(webview.is_loading());
+    }
+
+    // The per-signal updates below only touch the toolbar for the selected tab.
+    pub fn show_uri(&self, webview: &WebView) {
+        if !self.is_current(webview) { return; }
+        if let Some(uri) = webview.uri() { self.url_bar.set_text(newtab::display_uri(&uri)); }
+        favicons::update_image(&self.site_icon, webview);
+    }
+
+    pub fn show_site_icon(&self, webview: &WebView) {
+        if self.is_current(webview) { favicons::update_image(&self.site_icon, webview); }
+    }
+
+    pub fn show_title(&self, webview: &WebView) {
+        if let (true, Some(title)) = (self.is_current(webview), webview.title()) {
+            self.window.set_title(Some(&format!("{} - Rusty Browser", title)));
+        }
+    }
+
+    pub fn show_page_icons(&self, webview: &WebView) {
+        if !self.is_current(webview) { return; }
+        tls::update_security_icon(&self.url_bar, webview);
+        update_bookmark_icon(&self.url_bar, webview, &self.browser.bookmarks.borrow());
+    }
+
+    pub fn show_progress(&self, webview: &WebView) {
+        if !self.is_current(webview) { return; }
+        let progress = webview.estimated_load_progress();
+        self.progress_bar.set_fraction(progress);
+        self.progress_bar.set_visible(progress < 1.0);
+    }
+
+    // --- SETTINGS ---
+    fn show_settings(self: &Rc<Self>) {
+        let browser = &self.browser;
+        let dialog = Dialog::builder().transient_for(&self.window).modal(true).title("Settings").build();
+        dialog.add_button("Close", ResponseType::Close);
+        let content_area = dialog.content_area();
+        let scroll = ScrolledWindow::builder().hscrollbar_policy(PolicyType::Never).min_content_width(400).min_content_height(400).build();
+        let vbox = Box::new(Orientation::Vertical, 10);
+        vbox.set_margin_top(20); vbox.set_margin_bottom(20); vbox.set_margin_start(20); vbox.set_margin_end(20);
+
+        let state = browser.state.borrow();
+        let label_gen = Label::new(None); label_gen.set_markup("<b>General</b>"); label_gen.set_halign(gtk::Align::Start); vbox.append(&label_gen);
+        let home_entry = Entry::new(); home_entry.set_text(&state.homepage); vbox.append(&home_entry);
+        let new_tab_lbl = Label::new(Some("New Tabs Open")); new_tab_lbl.set_halign(gtk::Align::Start); vbox.append(&new_tab_lbl);
+        let new_tab_dropdown = DropDown::new(Some(StringList::new(&newtab::NEW_TAB_CHOICES)), Option::<gtk::Expression>::None);
+        new_tab_dropdown.set_selected(state.new_tab_page);
+        vbox.append(&new_tab_dropdown);
+        let restore_switch = Switch::new(); restore_switch.set_active(state.restore_session);
+        let restore_box = Box::new(Orientation::Horizontal, 10); restore_box.append(&restore_switch); restore_box.append(&Label::new(Some("Restore Tabs on Startup"))); vbox.append(&restore_box);
+        let vertical_switch = Switch::new(); vertical_switch.set_active(state.vertical_tabs);
+        let vertical_box = Box::new(Orientation::Horizontal, 10); vertical_box.append(&vertical_switch); vertical_box.append(&Label::new(Some("Vertical Tabs"))); vbox.append(&vertical_box);
+        let next_switch = Switch::new(); next_switch.set_active(state.tabs_next_to_current);
+        let next_box = Box::new(Orientation::Horizontal, 10); next_box.append(&next_switch); next_box.append(&Label::new(Some("Open New Tabs Next to the Current Tab"))); vbox.append(&next_box);
+        let sleep_lbl = Label::new(Some("Hibernate Background Tabs")); sleep_lbl.set_halign(gtk::Align::Start); vbox.append(&sleep_lbl);
+        let sleep_dropdown = DropDown::new(Some(StringList::new(&hibernation::choice_names())), Option::<gtk::Expression>::None);
+        sleep_dropdown.set_selected(state.hibernate_after);
+        vbox.append(&sleep_dropdown);
+        let show_home_switch = Switch::new(); show_home_switch.set_active(state.show_home_button);
+        let show_home_box = Box::new(Orientation::Horizontal, 10); show_home_box.append(&show_home_switch); show_home_box.append(&Label::new(Some("Show Home Button"))); vbox.append(&show_home_box);
+        let default_lbl = Label::new(Some("Checking default browser…")); default_lbl.set_halign(gtk::Align::Start); default_lbl.set_hexpand(true);
+        let default_btn = Button::builder().label("Make Default").build();
+        default_btn.add_css_class("flat-button");
+        default_btn.set_sensitive(false);
+        let default_box = Box::new(Orientation::Horizontal, 10); default_box.append(&default_lbl); default_box.append(&default_btn); vbox.append(&default_box);
+
+        vbox.append(&Separator::new(Orientation::Horizontal));
+        let label_search = Label::new(None); label_search.set_markup("<b>Search Engine</b>"); label_search.set_halign(gtk::Align::Start); vbox.append(&label_search);
+        let engine_names: Vec<&str> = SEARCH_ENGINES.iter().map(|(name, _)| *name).collect();
+        let engines = StringList::new(&engine_names);
+        let dropdown = DropDown::new(Some(engines), Option::<gtk::Expression>::None);
+        dropdown.set_selected(state.search_engine_index);
+        vbox.append(&dropdown);
+
+        vbox.append(&Separator::new(Orientation::Horizontal));
+        let label_perf = Label::new(None); label_perf.set_markup("<b>Performance &amp; Privacy</b>"); label_perf.set_halign(gtk::Align::Start); vbox.append(&label_perf);
+        let hw_switch = Switch::new(); hw_switch.set_active(state.use_hw_accel);
+        let hw_box = Box::new(Orientation::Horizontal, 10); hw_box.append(&hw_switch); hw_box.append(&Label::new(Some("Hardware Acceleration"))); vbox.append(&hw_box);
+        let ad_switch = Switch::new(); ad_switch.set_active(state.enable_adblock);
+        let ad_box = Box::new(Orientation::Horizontal, 10); ad_box.append(&ad_switch); ad_box.append(&Label::new(Some("AdBlock"))); vbox.append(&ad_box);
+        let amnesia_switch = Switch::new(); amnesia_switch.set_active(state.amnesia_mode);
+        let amnesia_box = Box::new(Orientation::Horizontal, 10); amnesia_box.append(&amnesia_switch); amnesia_box.append(&Label::new(Some("Amnesia Mode (applies on restart)"))); vbox.append(&amnesia_box);
+        if is_amnesia_session() {
+            let amnesia_lbl = Label::new(None); amnesia_lbl.set_markup("<i>This session is in Amnesia Mode. Settings are kept in memory only.</i>"); amnesia_lbl.set_halign(gtk::Align::Start); amnesia_lbl.set_wrap(true); vbox.append(&amnesia_lbl);
+        }
+        let warn_lbl = Label::new(None); warn_lbl.set_markup("<i>(Changes require opening a new tab)</i>"); warn_lbl.set_halign(gtk::Align::Start); warn_lbl.set_sensitive(false); vbox.append(&warn_lbl);
+        let https_switch = Switch::new(); https_switch.set_active(state.https_only);
+        let https_box = Box::new(Orientation::Horizontal, 10); https_box.append(&https_switch); https_box.append(&Label::new(Some("HTTPS-Only Mode"))); vbox.append(&https_box);
+        let https_exc_btn = Button::builder().label("HTTPS-Only Exceptions…").build();
+        https_exc_btn.add_css_class("flat-button");
+        vbox.append(&https_exc_btn);
+        let dnd_switch = Switch::new(); dnd_switch.set_active(state.notifications_dnd);
+        let dnd_box = Box::new(Orientation::Horizontal, 10); dnd_box.append(&dnd_switch); dnd_box.append(&Label::new(Some("Do Not Disturb (silence web notifications)"))); vbox.append(&dnd_box);
+        let clear_exit_switch = Switch::new(); clear_exit_switch.set_active(state.clear_on_exit);
+        let clear_exit_box = Box::new(Orientation::Horizontal, 10); clear_exit_box.append(&clear_exit_switch); clear_exit_box.append(&Label::new(Some("Clear Browsing Data on Exit"))); vbox.append(&clear_exit_box);
+        let clear_data_btn = Button::builder().label("Clear Browsing Data…").build();
+        clear_data_btn.add_css_class("flat-button");
+        vbox.append(&clear_data_btn);
+
+        vbox.append(&Separator::new(Orientation::Horizontal));
+        let label_cookies = Label::new(None); label_cookies.set_markup("<b>Cookies</b>"); label_cookies.set_halign(gtk::Align::Start); vbox.append(&label_cookies);
+        let cookie_dropdown = DropDown::new(Some(StringList::new(&site_data::COOKIE_POLICIES)), Option::<gtk::Expression>::None);
+        cookie_dropdown.set_selected(state.cookie_policy);
+        vbox.append(&cookie_dropdown);
+        let site_data_btn = Button::builder().label("Manage Cookies and Site Data…").build();
+        site_data_btn.add_css_class("flat-button");
+        vbox.append(&site_data_btn);
+        let permissions_btn = Button::builder().label("Site Permissions…").build();
+        permissions_btn.add_css_class("flat-button");
+        vbox.append(&permissions_btn);
+        drop(state);
+
+        vbox.append(&Separator::new(Orientation::Horizontal));
+        let label_about = Label::new(None); label_about.set_markup("<b>About</b>"); label_about.set_halign(gtk::Align::Start); vbox.append(&label_about);
+        let about_btn = Button::builder().label("About Rusty Browser").build();
+        about_btn.add_css_class("flat-button");
+        vbox.append(&about_btn);
+        vbox.append(&LinkButton::with_label("https://github.com/wyind/rusty_browser", "Source Code"));
+        vbox.append(&LinkButton::with_label("https://ko-fi.com/wyind", "Donate"));
+
+        scroll.set_child(Some(&vbox));
+        content_area.append(&scroll);
+
+        show_default_status(&default_lbl, &default_btn);
+        default_btn.connect_clicked(move |btn| {
+            btn.set_sensitive(false);
+            let (label, btn) = (default_lbl.downgrade(), btn.downgrade());
+            default_browser::make_default(move |_| {
+                if let (Some(label), Some(btn)) = (label.upgrade(), btn.upgrade()) { show_default_status(&label, &btn); }
+            });
+        });
+
+        let dialog_weak = dialog.downgrade();
+        let session_for_clear = browser.session.clone();
+        clear_data_btn.connect_clicked(move |_| {
+            if let Some(parent) = dialog_weak.upgrade() { clear_data::show_clear_data_dialog(&parent, &session_for_clear); }
+        });
+
+        let dialog_weak = dialog.downgrade();
+        let browser_for_sites = Rc::downgrade(browser);
+        site_data_btn.connect_clicked(move |_| {
+            if let (Some(parent), Some(browser)) = (dialog_weak.upgrade(), browser_for_sites.upgrade()) {
+                let browser_apply = Rc::downgrade(&browser);
+                let apply: Rc<dyn Fn()> = Rc::new(move || {
+                    if let Some(browser) = browser_apply.upgrade() { browser.apply_cookies(); }
+                });
+                site_data::show_site_data_window(&parent, &browser.session, browser.state.clone(), apply);
+            }
+        });
+
+        let dialog_weak = dialog.downgrade();
+        let perms_for_window = browser.permissions.clone();
+        permissions_btn.connect_clicked(move |_| {
+            if let Some(parent) = dialog_weak.upgrade() { permissions::show_permissions_window(&parent, &perms_for_window); }
+        });
+
+        let dialog_weak = dialog.downgrade();
+        let state_for_https = browser.state.clone();
+        https_exc_btn.connect_clicked(move |_| {
+            if let Some(parent) = dialog_weak.upgrade() { https_only::show_exceptions_window(&parent, &state_for_https); }
+        });
+
+        let window_weak_for_about = self.window.downgrade();
+        about_btn.connect_clicked(move |_| {
+            if let Some(parent_window) = window_weak_for_about.upgrade() {
+                let logo_bytes = include_bytes!("logo.png");
+                let loader = PixbufLoader::new();
+                loader.write(logo_bytes).unwrap();
+                loader.close().unwrap();
+                let pixbuf = loader.pixbuf().unwrap();
+                let texture = gdk::Texture::for_pixbuf(&pixbuf);
+
+                let about_window = Window::builder().transient_for(&parent_window).modal(true).title("About").default_width(350).default_height(400).resizable(false).decorated(true).build();
+                let vbox = Box::new(Orientation::Vertical, 15);
+                vbox.add_css_class("about-box");
+                vbox.set_margin_top(20); vbox.set_margin_bottom(20); vbox.set_margin_start(20); vbox.set_margin_end(20);
+
+                let img = Image::from_paintable(Some(&texture));
+                img.set_pixel_size(128);
+                vbox.append(&img);
+                let title = Label::new(None); title.set_markup("<span size='xx-large' weight='bold'>Rusty Browser</span>"); vbox.append(&title);
+                let version = Label::new(Some("Version 1.0.0 (Titan Edition)")); version.add_css_class("about-version"); vbox.append(&version);
+                let desc = Label::new(Some("A privacy-focused, high-performance browser.\nBuilt with Rust, GTK4 & WebKit."));
+                desc.set_wrap(true); desc.set_justify(gtk::Justification::Center); vbox.append(&desc);
+                vbox.append(&Separator::new(Orientation::Horizontal));
+                vbox.append(&LinkButton::with_label("https://wyind.dev", "Website"));
+                vbox.append(&LinkButton::with_label("https://github.com/wyind/rusty_browser", "Source Code"));
+                vbox.append(&LinkButton::with_label("https://ko-fi.com/wyind", "Buy Me A Coffee"));
+                vbox.append(&Label::new(Some("© 2025 wyind.dev")));
+
+                about_window.set_child(Some(&vbox));
+                about_window.present();
+            }
+        });
+
+        let browser_action = Rc::downgrade(browser);
+        dialog.connect_response(move |d, _| {
+            let browser = match browser_action.upgrade() { Some(b) => b, None => { d.close(); return; } };
+            let state_clone = &browser.state;
+            state_clone.borrow_mut().homepage = home_entry.text().to_string();
+            state_clone.borrow_mut().new_tab_page = new_tab_dropdown.selected();
+            state_clone.borrow_mut().restore_session = restore_switch.is_active();
+            state_clone.borrow_mut().vertical_tabs = vertical_switch.is_active();
+            state_clone.borrow_mut().tabs_next_to_current = next_switch.is_active();
+            state_clone.borrow_mut().hibernate_after = sleep_dropdown.selected();
+            state_clone.borrow_mut().use_hw_accel = hw_switch.is_active();
+            state_clone.borrow_mut().enable_adblock = ad_switch.is_active();
+            state_clone.borrow_mut().clear_on_exit = clear_exit_switch.is_active();
+            state_clone.borrow_mut().notifications_dnd = dnd_switch.is_active();
+            state_clone.borrow_mut().https_only = https_switch.is_active();
+            let amnesia = amnesia_switch.is_active();
+            let amnesia_changed = amnesia != state_clone.borrow().amnesia_mode;
+            state_clone.borrow_mut().amnesia_mode = amnesia;
+            state_clone.borrow_mut().show_home_button = show_home_switch.is_active();
+            browser.refresh_windows();
+
+            let idx = dropdown.selected();
+            state_clone.borrow_mut().search_engine_index = idx;
+            if let Some((_, url)) = SEARCH_ENGINES.get(idx as usize) {
+                state_clone.borrow_mut().search_engine_url = url.to_string();
+            }
+            let cookie_policy = cookie_dropdown.selected();
+            if cookie_policy != state_clone.borrow().cookie_policy {
+                state_clone.borrow_mut().cookie_policy = cookie_policy;
+                browser.apply_cookies();
+            }
+
+            // SAVE CONFIG
+            save_config(&state_clone.borrow());
+            if amnesia_changed && is_amnesia_session() { save_amnesia_preference(amnesia); }
+            d.close();
+        });
+        dialog.show();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{loads_on_restore, resolve_input};
+    use crate::AppConfig;
+
+    #[test]
+    fn resolve_input_tells_searches_from_addresses() {
+        let state = AppConfig::default();
+        let search = |q: &str| format!("{}{}", state.search_engine_url, q);
+        assert_eq!(resolve_input("foo", &state), search("foo"));
+        assert_eq!(resolve_input("a b.com", &state), search("a b.com"));
+        assert_eq!(resolve_input("foo.com", &state), "https://foo.com");
+        assert_eq!(resolve_input("http://foo.com/x", &state), "http://foo.com/x");
+    }
+
+    #[test]
+    fn resolve_input_keeps_internal_pages() {
+        let state = AppConfig::default();
+        assert_eq!(resolve_input("rusty:history", &state), "rusty:history");
+        assert_eq!(resolve_input("about:blank", &state), "about:blank");
+    }
+
+    #[test]
+    fn restores_load_only_the_selected_tab() {
+        let loaded: Vec<bool> = (0..3).map(|i| loads_on_restore(i, Some(1))).collect();
+        assert_eq!(loaded, [false, true, false]);
+        assert!((0..3).all(|i| !loads_on_restore(i, None)));
+        assert!((0..3).all(|i| !loads_on_restore(i, Some(7))));
+    }
+}
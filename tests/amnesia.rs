@@ -0,0 +1,159 @@
+// Runs a real browser session in amnesia mode with HOME and every XDG base
+// directory pointed into a scratch directory, stops it with SIGTERM, then
+// checks nothing was written and the scratch cache directory is gone. Run
+// once with XDG_RUNTIME_DIR and once without, where the scratch directory
+// falls back to the temp directory. Needs an X11 display, so these only run
+// with `cargo test -- --ignored`.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const STORAGE_PAGE: &str = r#"<!DOCTYPE html>
+<html><body>
+<p>amnesia test</p>
+<script>
+    document.cookie = "amnesia=1; max-age=3600";
+    try { localStorage.setItem("amnesia", "1"); } catch (e) {}
+    try { sessionStorage.setItem("amnesia", "1"); } catch (e) {}
+    try {
+        const req = indexedDB.open("amnesia", 1);
+        req.onupgradeneeded = () => req.result.createObjectStore("store");
+    } catch (e) {}
+</script>
+</body></html>"#;
+
+type Snapshot = BTreeMap<PathBuf, (u64, Option<SystemTime>)>;
+
+fn snapshot(dir: &Path, out: &mut Snapshot) {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if let Ok(meta) = entry.metadata() {
+            out.insert(path.clone(), (meta.len(), meta.modified().ok()));
+            if meta.is_dir() {
+                snapshot(&path, out);
+            }
+        }
+    }
+}
+
+// `with_runtime_dir` decides whether the browser sees XDG_RUNTIME_DIR; without
+// it, TMPDIR points into the test's own directory instead.
+fn run_amnesia_session(name: &str, with_runtime_dir: bool) {
+    assert!(env::var_os("DISPLAY").is_some(), "the amnesia test needs an X11 display (DISPLAY is unset)");
+
+    let root = env::temp_dir().join(format!("rusty_browser-amnesia-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let home = root.join("home");
+    let config = home.join(".config");
+    let data = home.join(".local/share");
+    let cache = home.join(".cache");
+    let state = home.join(".local/state");
+    let runtime = root.join("runtime");
+    let tmp = root.join("tmp");
+    for dir in [&config, &data, &cache, &state, &runtime, &tmp] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    fs::set_permissions(&runtime, fs::Permissions::from_mode(0o700)).unwrap();
+
+    // The page lives outside HOME so it doesn't show up in the snapshot.
+    let page = root.join("storage.html");
+    fs::write(&page, STORAGE_PAGE).unwrap();
+
+    // A pre-existing settings file, so the session loads the local page and
+    // we also catch it being rewritten.
+    let settings = serde_json::json!({
+        "homepage": format!("file://{}", page.display()),
+        "use_hw_accel": false,
+        "enable_adblock": true,
+        "amnesia_mode": false,
+        "show_home_button": true,
+        "search_engine_url": "https://duckduckgo.com/?q=",
+        "search_engine_index": 0
+    });
+    let settings_dir = config.join("rusty_browser");
+    fs::create_dir_all(&settings_dir).unwrap();
+    fs::write(settings_dir.join("settings.json"), serde_json::to_string_pretty(&settings).unwrap()).unwrap();
+
+    let mut before = Snapshot::new();
+    snapshot(&home, &mut before);
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_rusty_browser"));
+    cmd.arg("--amnesia")
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", &config)
+        .env("XDG_DATA_HOME", &data)
+        .env("XDG_CACHE_HOME", &cache)
+        .env("XDG_STATE_HOME", &state)
+        .env("TMPDIR", &tmp);
+    if with_runtime_dir {
+        cmd.env("XDG_RUNTIME_DIR", &runtime);
+    } else {
+        cmd.env_remove("XDG_RUNTIME_DIR");
+    }
+    if env::var_os("XAUTHORITY").is_none() {
+        if let Some(real_home) = env::var_os("HOME") {
+            cmd.env("XAUTHORITY", Path::new(&real_home).join(".Xauthority"));
+        }
+    }
+
+    let mut child = cmd.spawn().expect("failed to launch rusty_browser");
+    let scratch_base = if with_runtime_dir { &runtime } else { &tmp };
+    let scratch = scratch_base.join(format!("rusty_browser-amnesia-{}", child.id()));
+    thread::sleep(Duration::from_secs(8));
+    let still_running = child.try_wait().unwrap().is_none();
+    let scratch_created = scratch.exists();
+
+    // Stop it the way a logout would, so it gets to clean up after itself.
+    let _ = Command::new("kill").args(["-TERM", &child.id().to_string()]).status();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut exited = false;
+    while Instant::now() < deadline {
+        if child.try_wait().unwrap().is_some() {
+            exited = true;
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    if !exited {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    let scratch_removed = !scratch.exists();
+
+    let mut after = Snapshot::new();
+    snapshot(&home, &mut after);
+    let _ = fs::remove_dir_all(&root);
+
+    assert!(still_running, "browser exited early (is another instance already running?)");
+    assert!(exited, "browser did not exit within 10s of SIGTERM");
+    assert!(scratch_created, "no scratch directory at {}", scratch.display());
+    assert!(scratch_removed, "scratch directory {} was left behind", scratch.display());
+    let changed: Vec<_> = after
+        .iter()
+        .filter(|(path, meta)| before.get(*path) != Some(meta))
+        .map(|(path, _)| path.clone())
+        .collect();
+    assert!(changed.is_empty(), "amnesia session wrote to disk: {:?}", changed);
+}
+
+#[test]
+#[ignore = "needs an X11 display; run with `cargo test -- --ignored`"]
+fn amnesia_session_writes_nothing() {
+    run_amnesia_session("runtime", true);
+}
+
+#[test]
+#[ignore = "needs an X11 display; run with `cargo test -- --ignored`"]
+fn amnesia_session_without_runtime_dir_writes_nothing() {
+    run_amnesia_session("tmp", false);
+}